@@ -0,0 +1,246 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    error::{error, PitaError, Warning},
+    id::{gensym, value_from_id, Id, IdImpl},
+    location::Location,
+    value::{Decl, PatternExpr, Predicate, Signature, Value},
+};
+
+/// A name along with the expression it is bound to.
+pub(crate) type Binding = (Id, Value);
+
+pub(crate) struct DefBuilder {
+    name: Id,
+    arity: usize,
+    variant: DefBuilderVariant,
+    signature: Option<Signature>,
+}
+
+pub(crate) enum DefBuilderVariant {
+    // f = 3.14
+    Value(Value),
+    // f x 3 = 9001
+    // f x y = (g x)
+    Patterns(Vec<PatternExpr>),
+}
+
+/// Add a clause to the definition of its name. Clauses are kept in source order, and must all
+/// take the same number of arguments. A value without arguments has exactly one definition, and
+/// the clauses of a function all come from the same file, so that a program cannot add clauses
+/// to a function of the prelude.
+pub(crate) fn merge_decl(
+    all_symbols: &mut HashMap<String, DefBuilder>,
+    decl: Decl,
+) -> Result<(), PitaError> {
+    let name = decl.name.to_string();
+    let location = decl.name.location();
+    let Some(def_builder) = all_symbols.get_mut(&name) else {
+        all_symbols.insert(
+            name,
+            DefBuilder {
+                name: decl.name,
+                arity: decl.patterns.len(),
+                variant: if decl.patterns.is_empty() {
+                    DefBuilderVariant::Value(decl.body)
+                } else {
+                    DefBuilderVariant::Patterns(vec![PatternExpr {
+                        predicate: params_predicate(decl.patterns),
+                        expr: decl.body,
+                    }])
+                },
+                signature: None,
+            },
+        );
+        return Ok(());
+    };
+    let first = def_builder.name.location();
+    match &mut def_builder.variant {
+        _ if first.filename != location.filename => {
+            Err(error!("{location}: {name} is already defined at {first}"))
+        }
+        DefBuilderVariant::Value(_) if decl.patterns.is_empty() => {
+            Err(error!("{location}: {name} is already defined at {first}"))
+        }
+        DefBuilderVariant::Value(_) => Err(error!(
+            "{location}: {name} is defined with arguments here, but as a value without \
+             arguments at {first}"
+        )),
+        DefBuilderVariant::Patterns(_) if decl.patterns.is_empty() => Err(error!(
+            "{location}: {name} is defined as a value without arguments here, but with \
+             arguments at {first}"
+        )),
+        DefBuilderVariant::Patterns(_) if def_builder.arity != decl.patterns.len() => Err(error!(
+            "{location}: this clause for {name} takes {} arguments, but the clause at \
+                 {first} takes {}",
+            decl.patterns.len(),
+            def_builder.arity
+        )),
+        DefBuilderVariant::Patterns(pattern_exprs) => {
+            pattern_exprs.push(PatternExpr {
+                predicate: params_predicate(decl.patterns),
+                expr: decl.body,
+            });
+            Ok(())
+        }
+    }
+}
+
+/// Warn about functions whose clauses are interrupted by other declarations, which is usually a
+/// sign that a clause was meant for a different function.
+pub(crate) fn check_contiguous(decls: &[Decl]) -> Vec<Warning> {
+    let mut last_clause: HashMap<&str, usize> = HashMap::new();
+    let mut warned = HashSet::new();
+    let mut warnings = Vec::new();
+    for (i, decl) in decls.iter().enumerate() {
+        let name = decl.name.name();
+        if let Some(previous) = last_clause.insert(name, i) {
+            if previous + 1 != i && warned.insert(name) {
+                warnings.push(Warning {
+                    location: decl.name.location(),
+                    message: format!(
+                        "clauses for {name} are not contiguous; the previous clause is at {}",
+                        decls[previous].name.location()
+                    ),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Associate a type signature with the definition it describes.
+pub(crate) fn merge_signature(
+    all_symbols: &mut HashMap<String, DefBuilder>,
+    signature: Signature,
+) -> Result<(), PitaError> {
+    let name = &signature.name;
+    let Some(def_builder) = all_symbols.get_mut(name.name()) else {
+        return Err(error!(
+            "{}: the type signature for {name} lacks an accompanying definition",
+            name.location()
+        ));
+    };
+    if let Some(existing) = &def_builder.signature {
+        return Err(error!(
+            "{}: duplicate type signature for {name}, which was first declared at {}",
+            name.location(),
+            existing.name.location()
+        ));
+    }
+    def_builder.signature = Some(signature);
+    Ok(())
+}
+
+pub(crate) fn build_symbol(def_builder: DefBuilder) -> Result<(Id, Value), PitaError> {
+    match def_builder.variant {
+        DefBuilderVariant::Patterns(pattern_exprs) => {
+            assert!(!pattern_exprs.is_empty());
+            let location = def_builder.name.location();
+            Ok((
+                def_builder.name,
+                build_function(def_builder.arity, pattern_exprs, location),
+            ))
+        }
+        DefBuilderVariant::Value(value) => Ok((def_builder.name, value)),
+    }
+}
+
+/// Build a function of `arity` arguments which matches them, as a tuple, against the predicate
+/// of each of `pattern_exprs` in turn.
+fn build_function(arity: usize, pattern_exprs: Vec<PatternExpr>, location: Location) -> Value {
+    // Create callsite bindings.
+    let param_names: Vec<Id> = (0..arity).map(|_| gensym(location)).collect();
+    // Building this:
+    // f = \x.\y.\z. match (x, y, z) {
+    //   <pattern_exprs...>
+    // }
+    let inner_body = Value::Match {
+        // Match multi-parameter function arguments with tuples.
+        subject: Box::new(Value::Tuple {
+            dims: param_names
+                .iter()
+                .map(value_from_id::<IdImpl>)
+                .collect::<Vec<Value>>(),
+        }),
+        pattern_exprs,
+        location,
+    };
+    build_lambdas(param_names, inner_body)
+}
+
+/// Build `\x.\y.\z. body`.
+pub(crate) fn build_lambdas(params: Vec<Id>, body: Value) -> Value {
+    params
+        .into_iter()
+        .rev()
+        .fold(body, |value, acc| Value::Lambda {
+            param: acc,
+            body: Box::new(value),
+        })
+}
+
+/// Build the lambda `\params -> body` which starts at `location`. Parameters which are all
+/// plain names are bound directly; otherwise the arguments are matched the same way as those of
+/// a top-level function.
+pub(crate) fn build_lambda(params: Vec<Predicate>, body: Value, location: Location) -> Value {
+    let names: Option<Vec<Id>> = params
+        .iter()
+        .map(|param| match param {
+            Predicate::Irrefutable(id) => Some(id.clone()),
+            _ => None,
+        })
+        .collect();
+    match names {
+        Some(names) => build_lambdas(names, body),
+        None => build_function(
+            params.len(),
+            vec![PatternExpr {
+                predicate: params_predicate(params),
+                expr: body,
+            }],
+            location,
+        ),
+    }
+}
+
+/// Match the arguments of a function as a tuple, which is located at the first parameter.
+fn params_predicate(params: Vec<Predicate>) -> Predicate {
+    let location = params[0].location();
+    Predicate::Tuple(params, location)
+}
+
+/// Group the clauses of each name in `decls` into a single binding per name.
+pub(crate) fn build_bindings(decls: Vec<Decl>) -> Result<Vec<Binding>, PitaError> {
+    Ok(build_signed_bindings(decls, Vec::new())?.0)
+}
+
+/// Like `build_bindings`, but also checks that each of `signatures` describes one of the
+/// bindings, and returns them alongside.
+pub(crate) fn build_signed_bindings(
+    decls: Vec<Decl>,
+    signatures: Vec<Signature>,
+) -> Result<(Vec<Binding>, Vec<Signature>), PitaError> {
+    let mut all_symbols = HashMap::new();
+    // Bindings are built in order of their first clause.
+    let mut order = Vec::new();
+    for decl in decls {
+        if !all_symbols.contains_key(decl.name.name()) {
+            order.push(decl.name.to_string());
+        }
+        merge_decl(&mut all_symbols, decl)?;
+    }
+    for signature in signatures {
+        merge_signature(&mut all_symbols, signature)?;
+    }
+    let mut bindings = Vec::new();
+    let mut signatures = Vec::new();
+    for name in order {
+        let mut def_builder = all_symbols
+            .remove(&name)
+            .expect("every name has a definition");
+        signatures.extend(def_builder.signature.take());
+        bindings.push(build_symbol(def_builder)?);
+    }
+    Ok((bindings, signatures))
+}
@@ -1,7 +1,21 @@
 #![allow(dead_code)]
-use std::rc::Rc;
+use std::{cmp::Ordering, rc::Rc};
 
-use crate::{id::Id, runtime::error::RuntimeError, value::Value};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::{
+    id::Id,
+    parser::builtin_signature,
+    runtime::error::RuntimeError,
+    value::{Builtin, BuiltinFn, CtorId, TypeExpr, Value},
+};
+
+fn parse_signature(signature: &str) -> TypeExpr {
+    builtin_signature(signature)
+        .unwrap_or_else(|error| panic!("invalid builtin signature {signature:?}: {error}"))
+}
 
 #[derive(Clone)]
 pub(crate) struct Env {
@@ -17,18 +31,175 @@ impl Env {
 
     pub fn with_builtins() -> Self {
         let mut env = Self::new();
-        env.add_builtin("+", |args| {
-            if let [Value::Int(a), Value::Int(b)] = &args[..] {
-                Ok(Value::Int(a + b))
-            } else {
-                Err(RuntimeError::InvalidCallsite(
-                    "+ requires two integers".into(),
-                ))
+        // The prelude's instances of `Num`, `Eq`, `Ord` and `Show` for primitive types are
+        // defined with these.
+        env.add_int_operator("primAddInt", i64::checked_add, |a, b| a + b);
+        env.add_int_operator("primSubInt", i64::checked_sub, |a, b| a - b);
+        env.add_int_operator("primMulInt", i64::checked_mul, |a, b| a * b);
+        env.add_primitive_comparisons("Int", |a, b| match (a, b) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            _ => Some(a.to_bigint()?.cmp(&b.to_bigint()?)),
+        });
+        env.add_primitive_comparisons("Float", |a, b| match (a, b) {
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            _ => None,
+        });
+        env.add_primitive_comparisons("Char", |a, b| match (a, b) {
+            (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+            _ => None,
+        });
+        env.add_primitive_comparisons("String", |a, b| match (a, b) {
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            _ => None,
+        });
+        env.add_int_division("div", Integer::div_floor, Integer::div_floor);
+        env.add_int_division("mod", Integer::mod_floor, Integer::mod_floor);
+        env.add_float_operator("+.", |a, b| a + b);
+        env.add_float_operator("-.", |a, b| a - b);
+        env.add_float_operator("*.", |a, b| a * b);
+        env.add_float_operator("/.", |a, b| a / b);
+        env.add_float_comparison("==.", |a, b| a == b);
+        env.add_float_comparison("/=.", |a, b| a != b);
+        env.add_float_comparison("<.", |a, b| a < b);
+        env.add_float_comparison("<=.", |a, b| a <= b);
+        env.add_float_comparison(">.", |a, b| a > b);
+        env.add_float_comparison(">=.", |a, b| a >= b);
+        env.add_builtin("toFloat", "Int -> Float", 1, |args| {
+            match args[0].to_bigint().and_then(|x| x.to_f64()) {
+                Some(x) => Ok(Value::Float(x)),
+                None => Err(RuntimeError::InvalidCallsite(
+                    "toFloat requires an integer".to_string(),
+                )),
+            }
+        });
+        env.add_builtin("truncate", "Float -> Int", 1, |args| match &args[..] {
+            [Value::Float(x)] => BigInt::from_f64(x.trunc())
+                .map(Value::integer)
+                .ok_or_else(|| RuntimeError::Arithmetic(format!("truncate: {x:?} is not finite"))),
+            _ => Err(RuntimeError::InvalidCallsite(
+                "truncate requires a float".to_string(),
+            )),
+        });
+        env.add_builtin("<>", "String -> String -> String", 2, |args| {
+            match &args[..] {
+                [Value::Str(a), Value::Str(b)] => Ok(Value::Str(format!("{a}{b}"))),
+                _ => Err(RuntimeError::InvalidCallsite(
+                    "<> requires strings".to_string(),
+                )),
             }
         });
         // Add other builtins...
         env
     }
+
+    /// Add an integer operator which is computed on `i64`s when `small` does not overflow, and
+    /// on big integers otherwise.
+    fn add_int_operator(
+        &mut self,
+        name: &'static str,
+        small: fn(i64, i64) -> Option<i64>,
+        big: fn(BigInt, BigInt) -> BigInt,
+    ) {
+        self.add_builtin(name, "Int -> Int -> Int", 2, move |args| {
+            if let [Value::Int(a), Value::Int(b)] = &args[..] {
+                if let Some(result) = small(*a, *b) {
+                    return Ok(Value::Int(result));
+                }
+            }
+            let (a, b) = int_args(name, &args)?;
+            Ok(Value::integer(big(a, b)))
+        });
+    }
+
+    /// Add an integer division, which is an error when the divisor is zero.
+    fn add_int_division(
+        &mut self,
+        name: &'static str,
+        small: fn(&i64, &i64) -> i64,
+        big: fn(&BigInt, &BigInt) -> BigInt,
+    ) {
+        self.add_builtin(name, "Int -> Int -> Int", 2, move |args| match &args[..] {
+            // Big integers are never zero, since they do not fit in an `i64`.
+            [_, Value::Int(0)] => Err(RuntimeError::Arithmetic(format!(
+                "{name}: division by zero"
+            ))),
+            // Only `i64::MIN` divided by -1 overflows.
+            [Value::Int(a), Value::Int(b)] if *a != i64::MIN || *b != -1 => {
+                Ok(Value::Int(small(a, b)))
+            }
+            _ => {
+                let (a, b) = int_args(name, &args)?;
+                Ok(Value::integer(big(&a, &b)))
+            }
+        });
+    }
+
+    /// Add `primEq{ty}`, `primCompare{ty}` and `primShow{ty}` for the primitive type `ty`, whose
+    /// values are ordered by `compare`. Values which `compare` cannot order are unequal.
+    fn add_primitive_comparisons(
+        &mut self,
+        ty: &'static str,
+        compare: fn(&Value, &Value) -> Option<Ordering>,
+    ) {
+        self.add_builtin(
+            &format!("primEq{ty}"),
+            &format!("{ty} -> {ty} -> Bool"),
+            2,
+            move |args| {
+                Ok(Value::bool(
+                    compare(&args[0], &args[1]) == Some(Ordering::Equal),
+                ))
+            },
+        );
+        self.add_builtin(
+            &format!("primCompare{ty}"),
+            &format!("{ty} -> {ty} -> Ordering"),
+            2,
+            move |args| match compare(&args[0], &args[1]) {
+                Some(ordering) => Ok(Value::ordering(ordering)),
+                None => Err(RuntimeError::InvalidCallsite(format!(
+                    "primCompare{ty} cannot order {:?} and {:?}",
+                    args[0], args[1]
+                ))),
+            },
+        );
+        self.add_builtin(
+            &format!("primShow{ty}"),
+            &format!("{ty} -> String"),
+            1,
+            |args| {
+                Ok(Value::Str(match &args[0] {
+                    Value::Str(x) => format!("{x:?}"),
+                    value => format!("{value:?}"),
+                }))
+            },
+        );
+    }
+
+    fn add_float_operator(&mut self, name: &'static str, op: fn(f64, f64) -> f64) {
+        self.add_builtin(name, "Float -> Float -> Float", 2, move |args| {
+            if let [Value::Float(a), Value::Float(b)] = &args[..] {
+                Ok(Value::Float(op(*a, *b)))
+            } else {
+                Err(RuntimeError::InvalidCallsite(format!(
+                    "{name} requires two floats"
+                )))
+            }
+        });
+    }
+
+    fn add_float_comparison(&mut self, name: &'static str, op: fn(f64, f64) -> bool) {
+        self.add_builtin(name, "Float -> Float -> Bool", 2, move |args| {
+            if let [Value::Float(a), Value::Float(b)] = &args[..] {
+                Ok(Value::bool(op(*a, *b)))
+            } else {
+                Err(RuntimeError::InvalidCallsite(format!(
+                    "{name} requires two floats"
+                )))
+            }
+        });
+    }
+
     pub fn has_symbol(&self, symbol: &str) -> bool {
         self.bindings.contains_key(symbol)
     }
@@ -49,13 +220,88 @@ impl Env {
         }
     }
 
-    pub fn add_builtin<F>(&mut self, name: &str, f: F)
+    /// Add a data constructor. Nullary constructors are values, and the rest are lazy builtins
+    /// which build a `Value::Ctor` from their arguments.
+    pub fn add_ctor(&mut self, name: CtorId, arity: usize, signature: TypeExpr) {
+        let symbol = name.name().to_string();
+        if arity == 0 {
+            self.bindings.insert_mut(
+                symbol,
+                Value::Ctor {
+                    name,
+                    dims: Vec::new(),
+                },
+            );
+        } else {
+            let func = move |dims| {
+                Ok(Value::Ctor {
+                    name: name.clone(),
+                    dims,
+                })
+            };
+            self.insert_builtin(&symbol, arity, signature, false, Box::new(func));
+        }
+    }
+
+    /// Add a builtin whose arguments are evaluated to WHNF before it is called. The signature is
+    /// written in pita syntax, such as `Int -> Int -> Int`.
+    pub fn add_builtin<F>(&mut self, name: &str, signature: &str, arity: usize, f: F)
     where
         F: Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static,
     {
-        self.bindings
-            .insert_mut(name.to_string(), Value::builtin(Rc::new(f)));
+        self.insert_builtin(name, arity, parse_signature(signature), true, Box::new(f));
+    }
+
+    /// Add a builtin which receives its arguments unevaluated.
+    pub fn add_lazy_builtin<F>(&mut self, name: &str, signature: &str, arity: usize, f: F)
+    where
+        F: Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static,
+    {
+        self.insert_builtin(name, arity, parse_signature(signature), false, Box::new(f));
+    }
+
+    fn insert_builtin(
+        &mut self,
+        name: &str,
+        arity: usize,
+        signature: TypeExpr,
+        strict: bool,
+        func: Box<BuiltinFn>,
+    ) {
+        assert!(arity > 0, "builtin {name} must take arguments");
+        self.bindings.insert_mut(
+            name.to_string(),
+            Value::builtin(Rc::new(Builtin {
+                name: name.to_string(),
+                arity,
+                signature,
+                strict,
+                func,
+            })),
+        );
+    }
+
+    /// The value of every symbol in the env.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.bindings.values()
+    }
+
+    /// Every builtin in the env, including constructor functions.
+    pub fn builtins(&self) -> impl Iterator<Item = &Builtin> {
+        self.bindings.values().filter_map(|value| match value {
+            Value::Builtin(builtin) => Some(&**builtin),
+            _ => None,
+        })
+    }
+}
+
+/// The arguments of a binary integer builtin, as big integers.
+fn int_args(name: &str, args: &[Value]) -> Result<(BigInt, BigInt), RuntimeError> {
+    match args {
+        [a, b] => a.to_bigint().zip(b.to_bigint()),
+        _ => None,
     }
+    .ok_or_else(|| RuntimeError::InvalidCallsite(format!("{name} requires two integers")))
 }
 
 impl std::fmt::Debug for Env {
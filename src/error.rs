@@ -4,6 +4,7 @@ use nom_language::error::VerboseError;
 use crate::{
     id::{IdError, IdErrorTrait},
     runtime::error::RuntimeError,
+    typecheck::error::TypeError,
 };
 
 macro_rules! error {
@@ -27,6 +28,19 @@ impl std::fmt::Display for PitaError {
     }
 }
 
+/// A problem which does not prevent a program from running, but probably indicates a mistake.
+#[derive(Debug)]
+pub struct Warning {
+    pub location: crate::location::Location,
+    pub message: String,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: pita warning: {}", self.location, self.message)
+    }
+}
+
 impl<C: IdErrorTrait> From<IdError<C>> for PitaError {
     #[track_caller]
     fn from(e: IdError<C>) -> Self {
@@ -51,6 +65,12 @@ impl From<RuntimeError> for PitaError {
         )
     }
 }
+impl From<TypeError> for PitaError {
+    #[track_caller]
+    fn from(e: TypeError) -> Self {
+        Self(format!("type error: {e}"), std::panic::Location::caller())
+    }
+}
 impl From<std::io::Error> for PitaError {
     #[track_caller]
     fn from(e: std::io::Error) -> Self {
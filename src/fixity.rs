@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use crate::{
+    decl::Binding,
+    error::{error, PitaError},
+    id::{prelude_alias, Id},
+    value::{Assoc, FixityDecl, Value},
+};
+
+/// The fixity of an operator without a fixity declaration.
+const DEFAULT_FIXITY: Fixity = Fixity {
+    assoc: Assoc::Left,
+    precedence: 9,
+};
+
+/// Negation binds as `-` does in the prelude, so that `- a * b` is `negate (a * b)` and `- a + b`
+/// is `negate a + b`.
+const NEGATION_FIXITY: Fixity = Fixity {
+    assoc: Assoc::Left,
+    precedence: 6,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Fixity {
+    assoc: Assoc,
+    precedence: u8,
+}
+
+impl std::fmt::Display for Fixity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = match self.assoc {
+            Assoc::Left => "infixl",
+            Assoc::Right => "infixr",
+            Assoc::None => "infix",
+        };
+        write!(f, "{keyword} {}", self.precedence)
+    }
+}
+
+/// Group every chain of infix operators in `bindings` into callsites, according to the fixity
+/// declarations of the program. Fixities are global, so they apply to operators which are
+/// used before they are declared.
+pub(crate) fn resolve_fixities(
+    fixity_decls: &[FixityDecl],
+    bindings: &mut [Binding],
+) -> Result<(), PitaError> {
+    let mut fixities: HashMap<&str, (Fixity, &Id)> = HashMap::new();
+    for fixity_decl in fixity_decls {
+        let fixity = Fixity {
+            assoc: fixity_decl.assoc,
+            precedence: fixity_decl.precedence,
+        };
+        for operator in &fixity_decl.operators {
+            if let Some((_, first)) = fixities.insert(operator.name(), (fixity, operator)) {
+                return Err(error!(
+                    "{}: the fixity of {operator} is already declared at {}",
+                    operator.location(),
+                    first.location()
+                ));
+            }
+        }
+    }
+    let resolver = Resolver {
+        fixities: fixities
+            .into_iter()
+            .map(|(name, (fixity, _))| (name, fixity))
+            .collect(),
+    };
+    for (_, expr) in bindings {
+        resolver.resolve(expr)?;
+    }
+    Ok(())
+}
+
+struct Resolver<'a> {
+    fixities: HashMap<&'a str, Fixity>,
+}
+
+impl Resolver<'_> {
+    fn fixity(&self, operator: &Id) -> Fixity {
+        self.fixities
+            .get(operator.name())
+            .copied()
+            .unwrap_or(DEFAULT_FIXITY)
+    }
+
+    /// Replace every `Value::Infix` within `expr`, including `expr` itself.
+    fn resolve(&self, expr: &mut Value) -> Result<(), PitaError> {
+        match expr {
+            Value::Infix {
+                operands,
+                operators,
+                negations,
+            } => {
+                for operand in operands.iter_mut() {
+                    self.resolve(operand)?;
+                }
+                let mut operands = std::mem::take(negations)
+                    .into_iter()
+                    .zip(std::mem::take(operands));
+                let mut operators = std::mem::take(operators).into_iter().peekable();
+                let first = operands.next().expect("infix chains have an operand");
+                let first = self.negate(first, &mut operands, &mut operators)?;
+                *expr = self.group(None, first, &mut operands, &mut operators)?;
+            }
+            Value::Lambda { body, .. } => self.resolve(body)?,
+            Value::Match {
+                subject,
+                pattern_exprs,
+                ..
+            } => {
+                self.resolve(subject)?;
+                for pattern_expr in pattern_exprs {
+                    self.resolve(&mut pattern_expr.expr)?;
+                }
+            }
+            Value::Callsite { function, argument } => {
+                self.resolve(function)?;
+                self.resolve(argument)?;
+            }
+            Value::Guarded { alternatives, .. } => {
+                for (condition, body) in alternatives {
+                    self.resolve(condition)?;
+                    self.resolve(body)?;
+                }
+            }
+            Value::Tuple { dims } => {
+                for dim in dims {
+                    self.resolve(dim)?;
+                }
+            }
+            Value::Let { bindings, body } => {
+                for (_, expr) in bindings {
+                    self.resolve(expr)?;
+                }
+                self.resolve(body)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Apply operators to `lhs` for as long as they bind more tightly than `outer`, the operator
+    /// to the left of `lhs`. This is the resolution algorithm of the Haskell report.
+    fn group(
+        &self,
+        outer: Option<(&Id, Fixity)>,
+        mut lhs: Value,
+        operands: &mut impl Iterator<Item = (Option<Id>, Value)>,
+        operators: &mut std::iter::Peekable<impl Iterator<Item = Id>>,
+    ) -> Result<Value, PitaError> {
+        while let Some(operator) = operators.peek() {
+            let fixity = self.fixity(operator);
+            if let Some((outer, outer_fixity)) = outer {
+                if outer_fixity.precedence == fixity.precedence
+                    && (outer_fixity.assoc != fixity.assoc || fixity.assoc == Assoc::None)
+                {
+                    return Err(error!(
+                        "{}: cannot mix {outer} [{outer_fixity}] and {operator} [{fixity}] in \
+                         the same infix expression",
+                        operator.location()
+                    ));
+                }
+                if outer_fixity.precedence > fixity.precedence
+                    || (outer_fixity.precedence == fixity.precedence && fixity.assoc == Assoc::Left)
+                {
+                    return Ok(lhs);
+                }
+            }
+            let operator = operators.next().expect("the operator was peeked");
+            let rhs = operands.next().expect("operators are followed by operands");
+            let rhs = self.negate(rhs, operands, operators)?;
+            let rhs = self.group(Some((&operator, fixity)), rhs, operands, operators)?;
+            lhs = Value::Callsite {
+                function: Box::new(Value::Callsite {
+                    function: Box::new(Value::Id(operator)),
+                    argument: Box::new(lhs),
+                }),
+                argument: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    /// Apply the negation of `operand`, if any, to it along with the operators which follow it
+    /// and bind more tightly than negation.
+    fn negate(
+        &self,
+        (negation, operand): (Option<Id>, Value),
+        operands: &mut impl Iterator<Item = (Option<Id>, Value)>,
+        operators: &mut std::iter::Peekable<impl Iterator<Item = Id>>,
+    ) -> Result<Value, PitaError> {
+        let Some(minus) = negation else {
+            return Ok(operand);
+        };
+        let operand = self.group(
+            Some((&minus, NEGATION_FIXITY)),
+            operand,
+            operands,
+            operators,
+        )?;
+        Ok(Value::Callsite {
+            function: Box::new(Value::Id(prelude_alias("negate", minus.location()))),
+            argument: Box::new(operand),
+        })
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{location::Location, token::Token, value::Value};
 
 pub const KEYWORDS: &[&str] = &[
-    "<-", "->", ":", ";", "else", "if", "let", "match", "do", "then",
+    "..", "<-", "->", ":", "::", ";", "=", "=>", "|", "\\", "class", "data", "else", "if", "infix",
+    "infixl", "infixr", "instance", "let", "match", "do", "then", "where",
 ];
 
 pub fn parse_id<E: IdErrorTrait>(token: Token) -> Result<Id, crate::error::PitaError> {
@@ -39,6 +40,49 @@ pub fn internal_ctor_id(name: &str) -> Id {
     internal_id_impl::<CtorIdImpl>(name)
 }
 
+/// A reference to a constructor which the parser introduces at `location`, such as the `Cons` of
+/// a list pattern.
+pub fn ctor_id_at(name: &str, location: Location) -> Id {
+    debug_assert!(CtorIdImpl::is_valid(name));
+    Id {
+        token: Token {
+            text: name.to_string(),
+            location,
+        },
+    }
+}
+
+/// A reference to a value which the parser introduces at `location`, such as the `<>` which
+/// appends the parts of an interpolated string.
+pub fn id_at(name: &str, location: Location) -> Id {
+    debug_assert!(IdImpl::is_valid(name));
+    Id {
+        token: Token {
+            text: name.to_string(),
+            location,
+        },
+    }
+}
+
+/// The name of a binding which the type checker generates at `location`, such as the dictionary
+/// of an instance. Such names contain `$`, so they cannot clash with names in the source.
+pub fn derived_id(name: &str, location: Location) -> Id {
+    debug_assert!(name.contains('$'));
+    Id {
+        token: Token {
+            text: name.to_string(),
+            location,
+        },
+    }
+}
+
+/// The alias of the prelude function `name` which desugared syntax refers to, such as
+/// `prelude$concatMap` for list comprehensions. The alias is a derived name, so that local
+/// bindings cannot capture it.
+pub fn prelude_alias(name: &str, location: Location) -> Id {
+    derived_id(&format!("prelude${name}"), location)
+}
+
 pub fn internal_id_impl<E: IdErrorTrait>(name: &str) -> Id {
     debug_assert!(E::is_valid(name));
     parse_id::<E>(Token {
@@ -75,7 +119,7 @@ impl IdErrorTrait for IdImpl {
         text.chars()
             .next()
             .is_some_and(|c| c.is_alphabetic() || c.is_ascii_punctuation())
-            && !KEYWORDS.iter().any(|&kwd| kwd == text)
+            && !KEYWORDS.contains(&text)
     }
 }
 
@@ -89,7 +133,7 @@ impl IdErrorTrait for CtorIdImpl {
     fn is_valid(text: &str) -> bool {
         text.chars()
             .next()
-            .is_some_and(|c| c.is_alphabetic() || c.is_uppercase())
+            .is_some_and(|c| c.is_alphabetic() && c.is_uppercase())
     }
 }
 
@@ -1,4 +1,5 @@
-use nom_locate::LocatedSpan;
+use crate::parser::Span;
+
 pub type LocationFilename = &'static str;
 
 #[derive(Copy, Clone)]
@@ -16,12 +17,15 @@ impl Location {
             col: 0,
         }
     }
+    pub fn is_known(&self) -> bool {
+        self.line != 0
+    }
 }
 
-impl From<&LocatedSpan<&str, LocationFilename>> for Location {
-    fn from(span: &LocatedSpan<&str, LocationFilename>) -> Self {
+impl From<&Span<'_>> for Location {
+    fn from(span: &Span<'_>) -> Self {
         Self {
-            filename: span.extra,
+            filename: span.extra.filename,
             line: span.location_line(),
             col: span.naive_get_utf8_column() as u32,
         }
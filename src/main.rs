@@ -1,145 +1,198 @@
 //! Pita is a programming language for writing lazy functional programs.
+mod decl;
 mod env;
 mod error;
+mod fixity;
 mod id;
 mod location;
 mod parser;
+mod record;
 mod token;
 mod value;
 
 mod runtime {
     pub(crate) mod error;
+    pub(crate) mod matcher;
 }
 
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+mod typecheck {
+    pub(crate) mod classes;
+    mod deps;
+    pub(crate) mod elaborate;
+    pub(crate) mod error;
+    pub(crate) mod exhaustive;
+    pub(crate) mod infer;
+    pub(crate) mod types;
+}
+
+use std::{cell::RefCell, rc::Rc};
 
 use clap::Parser;
 use test_each_file::test_each_path;
 use value::Predicate;
 
 use crate::{
+    decl::{build_signed_bindings, check_contiguous},
     env::Env,
-    error::{error, PitaError},
-    id::{gensym, internal_id, value_from_id, Id, IdImpl},
-    runtime::error::RuntimeError,
-    value::{Decl, PatternExpr, Value},
+    error::{error, PitaError, Warning},
+    fixity::resolve_fixities,
+    id::{internal_id, prelude_alias, value_from_id, IdImpl},
+    location::Location,
+    parser::PRELUDE_ALIASES,
+    record::resolve_records,
+    runtime::{
+        error::RuntimeError,
+        matcher::{MatchStep, Matcher},
+    },
+    typecheck::{
+        classes::method_bindings, elaborate::elaborate, exhaustive::check_matches,
+        infer::check_program,
+    },
+    value::{Builtin, CtorId, DataDecl, Decl, Item, PatternExpr, ThunkEnv, ThunkState, Value},
 };
 
 #[derive(Parser)]
 struct Args {
     /// The file to execute
     filename: String,
+    /// Print the doc comments of NAME, from the prelude or the file, instead of executing it
+    #[arg(long, value_name = "NAME")]
+    doc: Option<String>,
 }
 
 fn main() -> Result<(), PitaError> {
     tracing_subscriber::fmt::init();
     let args = Args::parse();
+    if let Some(name) = args.doc {
+        let content = std::fs::read_to_string(&args.filename)?;
+        for (location, doc) in docs(args.filename.leak(), &content, &name)? {
+            println!("{location}:\n{doc}\n");
+        }
+        return Ok(());
+    }
     let value = run_program(args.filename)?;
     tracing::info!("{:#?}", value);
     Ok(())
 }
 
-struct DefBuilder {
-    name: Id,
-    arity: usize,
-    variant: DefBuilderVariant,
-}
-
-enum DefBuilderVariant {
-    // f = 3.14
-    Value(Value),
-    // f x 3 = 9001
-    // f x y = (g x)
-    Patterns(Vec<PatternExpr>),
-}
+/// Declarations which are available to every program.
+const PRELUDE: &str = include_str!("prelude.pita");
 
-fn merge_decl(all_symbols: &mut HashMap<String, DefBuilder>, decl: Decl) -> Result<(), PitaError> {
-    let name = decl.name.to_string();
-    if let Some(def_builder) = all_symbols.get_mut(&name) {
-        if def_builder.arity != decl.patterns.len() {
+/// Add the constructors and declarations of a program to `env`, once the program has been
+/// type checked and its overloaded bindings have been elaborated to pass dictionaries, along with
+/// any warnings about the program.
+fn build_env(mut env: Env, items: Vec<Item>) -> Result<(Env, Vec<Warning>), PitaError> {
+    let mut data_decls = Vec::new();
+    let mut class_decls = Vec::new();
+    let mut instance_decls = Vec::new();
+    let mut decls = Vec::new();
+    let mut accessors = Vec::new();
+    let mut signatures = Vec::new();
+    let mut fixity_decls = Vec::new();
+    for item in items {
+        match item {
+            Item::Decl(decl) => decls.push(decl),
+            Item::Signature(signature) => signatures.push(signature),
+            Item::Fixity(fixity_decl) => fixity_decls.push(fixity_decl),
+            Item::Class(class_decl) => class_decls.push(class_decl),
+            Item::Instance(instance_decl) => instance_decls.push(instance_decl),
+            Item::Data(data_decl) => {
+                let name = &data_decl.name;
+                if let Some(existing) = data_decls
+                    .iter()
+                    .find(|other: &&DataDecl| other.name.name() == name.name())
+                {
+                    return Err(error!(
+                        "{}: type {name} is already defined at {}",
+                        name.location(),
+                        existing.name.location()
+                    ));
+                }
+                for ctor in &data_decl.ctors {
+                    if env.has_symbol(ctor.name.name()) {
+                        return Err(error!(
+                            "{}: constructor {} is already defined",
+                            ctor.name.location(),
+                            ctor.name
+                        ));
+                    }
+                    env.add_ctor(
+                        CtorId::new(&ctor.name)?,
+                        ctor.fields.len(),
+                        data_decl.ctor_signature(ctor),
+                    );
+                }
+                for field in data_decl.field_names() {
+                    let existing = data_decls.iter().find_map(|other: &DataDecl| {
+                        let mut names = other.field_names().into_iter();
+                        let name = names.find(|name| name.name() == field.name())?;
+                        Some((&other.name, name.location()))
+                    });
+                    if let Some((other, first)) = existing {
+                        return Err(error!(
+                            "{}: {field} is already a field of {other} at {first}",
+                            field.location()
+                        ));
+                    }
+                }
+                accessors.extend(data_decl.accessors());
+                data_decls.push(data_decl);
+            }
+        }
+    }
+    // Accessors are only defined by their fields.
+    for decl in &decls {
+        if let Some(field) = accessors
+            .iter()
+            .find(|field: &&Decl| field.name.name() == decl.name.name())
+        {
             return Err(error!(
-                "a decl for {name} does not match another in terms of arguments count \
-                    [{}, {}]",
-                def_builder.arity,
-                decl.patterns.len()
+                "{}: {} is already defined as a field at {}",
+                decl.name.location(),
+                decl.name,
+                field.name.location()
             ));
         }
-    } else {
-        all_symbols.insert(
-            name,
-            DefBuilder {
-                name: decl.name,
-                arity: decl.patterns.len(),
-                variant: if decl.patterns.is_empty() {
-                    DefBuilderVariant::Value(decl.body)
-                } else {
-                    DefBuilderVariant::Patterns(vec![PatternExpr {
-                        predicate: Predicate::Tuple(decl.patterns),
-                        expr: decl.body,
-                    }])
-                },
-            },
-        );
     }
-    Ok(())
-}
-
-fn build_symbol(def_builder: DefBuilder) -> Result<(Id, Value), PitaError> {
-    match def_builder.variant {
-        DefBuilderVariant::Patterns(pattern_exprs) => {
-            assert!(!pattern_exprs.is_empty());
-            // Create callsite bindings.
-            let param_names: Vec<Id> = (0..def_builder.arity)
-                .map(|_| gensym(def_builder.name.location()))
-                .collect();
-            // Building this:
-            // f = \x.\y.\z. match (x, y, z) {
-            //   <pattern_exprs...>
-            // }
-            let inner_body = Value::Match {
-                // Match multi-parameter function arguments with tuples.
-                subject: Box::new(Value::Tuple {
-                    dims: param_names
-                        .iter()
-                        .map(value_from_id::<IdImpl>)
-                        .collect::<Vec<Value>>(),
-                }),
-                pattern_exprs,
-            };
-
-            // We have a predicate to match.
-            Ok((
-                def_builder.name,
-                param_names
-                    .into_iter()
-                    .rev()
-                    .fold(inner_body, |value, acc| Value::Lambda {
-                        param: acc,
-                        body: Box::new(value),
-                    }),
-            ))
-        }
-        DefBuilderVariant::Value(value) => Ok((
-            def_builder.name,
-            // Thunk the value since it is a singleton.
-            Value::Thunk {
-                // Env to be supplied by the runtime.
-                // TODO: mark this as viewing the global env somehow.
-                env: None,
-                expr: Rc::new(RefCell::new(value)),
-            },
-        )),
+    decls.extend(accessors);
+    let mut warnings = check_contiguous(&decls);
+    let (mut bindings, signatures) = build_signed_bindings(decls, signatures)?;
+    for name in PRELUDE_ALIASES {
+        let function = bindings
+            .iter()
+            .map(|(binding, _)| binding)
+            .chain(
+                class_decls
+                    .iter()
+                    .flat_map(|class_decl| class_decl.methods.iter().map(|method| &method.name)),
+            )
+            .find(|function| function.name() == name)
+            .unwrap_or_else(|| panic!("the prelude defines {name}"));
+        let alias = prelude_alias(name, function.location());
+        bindings.push((alias, Value::Id(function.clone())));
     }
-}
-
-fn build_env(all_symbols: &mut HashMap<String, DefBuilder>) -> Result<Env, PitaError> {
-    let mut env = Env::with_builtins();
-    for (_, def_builder) in all_symbols.drain() {
+    let methods = method_bindings(&env, &class_decls, &instance_decls, &bindings)?;
+    bindings.extend(methods);
+    resolve_records(&data_decls, &mut bindings)?;
+    resolve_fixities(&fixity_decls, &mut bindings)?;
+    let (class_bindings, dictionaries) = check_program(
+        &env,
+        &data_decls,
+        &class_decls,
+        &instance_decls,
+        &mut bindings,
+        &signatures,
+    )?;
+    warnings.extend(check_matches(&data_decls, &bindings));
+    bindings.extend(class_bindings);
+    elaborate(&mut bindings, &dictionaries);
+    for (name, value) in bindings {
         // This loop handles defining a single global variable as a function or otherwise.
-        let (name, value) = build_symbol(def_builder)?;
-        env.add_symbol_mut(name, value);
+        let location = name.location();
+        // Thunk the value since it is a singleton.
+        env.add_symbol_mut(name, Value::global_thunk(value, location));
     }
-    Ok(env)
+    Ok((env, warnings))
 }
 
 fn run_program(filename: impl AsRef<std::path::Path>) -> Result<Value, PitaError> {
@@ -148,18 +201,56 @@ fn run_program(filename: impl AsRef<std::path::Path>) -> Result<Value, PitaError
         return Err(error!("file {filename:?} does not exist"));
     }
     let content = std::fs::read_to_string(filename)?;
-    let filename = filename.display().to_string().leak();
-    let file_span = crate::parser::Span::new_extra(&content, filename);
-    let (remaining, decls) = parser::program_parser(file_span)?;
-    if remaining.len() != 0 {
-        return Err(error!("remaining input: {remaining:?}"));
-    }
-    let mut all_symbols = Default::default();
-    for decl in decls {
-        merge_decl(&mut all_symbols, decl)?;
+    run_source(filename.display().to_string().leak(), &content)
+}
+
+fn run_source(filename: &'static str, content: &str) -> Result<Value, PitaError> {
+    run_main(load_program(Env::with_builtins(), filename, content)?)
+}
+
+/// Parse a program, along with the prelude, into `env`, which supplies the builtins. Warnings
+/// are logged.
+fn load_program(env: Env, filename: &'static str, content: &str) -> Result<Env, PitaError> {
+    let (env, warnings) = compile_program(env, filename, content)?;
+    for warning in warnings {
+        tracing::warn!("{warning}");
     }
-    let env = build_env(&mut all_symbols)?;
+    Ok(env)
+}
+
+/// The doc comments of the items of the prelude and the program which declare `name`, along
+/// with where each item is.
+fn docs(
+    filename: &'static str,
+    content: &str,
+    name: &str,
+) -> Result<Vec<(Location, String)>, PitaError> {
+    let mut items = parse_items("<prelude>", PRELUDE)?;
+    items.extend(parse_items(filename, content)?);
+    Ok(items
+        .iter()
+        .filter(|item| item.names().iter().any(|item_name| item_name == name))
+        .filter_map(|item| Some((item.location(), item.doc()?.to_string())))
+        .collect())
+}
+
+fn compile_program(
+    env: Env,
+    filename: &'static str,
+    content: &str,
+) -> Result<(Env, Vec<Warning>), PitaError> {
+    let mut items = parse_items("<prelude>", PRELUDE)?;
+    items.extend(parse_items(filename, content)?);
+    build_env(env, items)
+}
+
+fn parse_items(filename: &'static str, content: &str) -> Result<Vec<Item>, PitaError> {
+    let file_span = parser::new_span(content, filename);
+    let (_, items) = parser::program_parser(file_span).map_err(parser::parse_error)?;
+    Ok(items)
+}
 
+fn run_main(env: Env) -> Result<Value, PitaError> {
     // Build an entrypoint which is a call to user `main`.
     let entrypoint = Value::Callsite {
         function: Box::new(value_from_id::<IdImpl>(&internal_id("main"))),
@@ -177,19 +268,228 @@ fn run_program(filename: impl AsRef<std::path::Path>) -> Result<Value, PitaError
 }
 
 fn eval_loop(env: Env, expr: Value) -> Result<Value, RuntimeError> {
-    tracing::info!(?expr, ?env, "evaluating expression");
+    tracing::trace!(?expr, "evaluating expression");
     enum Continuation {
         ApplyTo {
-            env: Env,
             arg: Value,
-            next: Box<Continuation>,
+            next: Next,
+        },
+        Match {
+            env: Env,
+            pattern_exprs: Vec<PatternExpr>,
+            location: Location,
+            next: Next,
+        },
+        ResumeMatch {
+            matcher: Box<Matcher>,
+            predicate: Predicate,
+            next: Next,
+        },
+        /// Take `body` if the condition of a guard is `True`, or else try the remaining
+        /// `alternatives`, which are in reverse order.
+        Guard {
+            env: Env,
+            body: Value,
+            alternatives: Vec<(Value, Value)>,
+            location: Location,
+            next: Next,
+        },
+        /// Resume `matcher` at its next clause if every guard of the matched clause fails.
+        FallThrough {
+            matcher: Box<Matcher>,
+            next: Next,
+        },
+        Update {
+            thunk: Rc<RefCell<ThunkState>>,
+            next: Next,
+        },
+        ForceArgs {
+            builtin: Rc<Builtin>,
+            forced: Vec<Value>,
+            // In reverse order, so that the next argument to force is at the end.
+            pending: Vec<Value>,
+            next: Next,
         },
         Done,
     }
+    impl Continuation {
+        fn next_mut(&mut self) -> Option<&mut Next> {
+            match self {
+                Continuation::ApplyTo { next, .. }
+                | Continuation::Match { next, .. }
+                | Continuation::ResumeMatch { next, .. }
+                | Continuation::Guard { next, .. }
+                | Continuation::FallThrough { next, .. }
+                | Continuation::Update { next, .. }
+                | Continuation::ForceArgs { next, .. } => Some(next),
+                Continuation::Done => None,
+            }
+        }
+    }
+    /// The continuation which follows another. A deep evaluation, such as of a long chain of
+    /// thunks, builds a long chain of continuations, so they are dropped one at a time rather than
+    /// recursively when evaluation fails partway through.
+    struct Next(Option<Box<Continuation>>);
+    impl Next {
+        fn new(continuation: Continuation) -> Self {
+            Self(Some(Box::new(continuation)))
+        }
+        fn take(mut self) -> Continuation {
+            *self.0.take().expect("a continuation is only taken once")
+        }
+    }
+    impl Drop for Next {
+        fn drop(&mut self) {
+            let mut next = self.0.take();
+            while let Some(mut continuation) = next {
+                next = continuation.next_mut().and_then(|next| next.0.take());
+            }
+        }
+    }
     enum State {
         Walk { env: Env, expr: Value },
         ContinueWith(Value),
     }
+    /// Evaluate a runtime value to WHNF. Pending thunks are blackholed until the `Update`
+    /// continuation overwrites them with their WHNF.
+    fn force(
+        value: Value,
+        next: Continuation,
+        global_env: &Env,
+    ) -> Result<(State, Continuation), RuntimeError> {
+        let Value::Thunk(thunk) = value else {
+            return Ok((State::ContinueWith(value), next));
+        };
+        let (env, expr) = {
+            let mut thunk_state = thunk.borrow_mut();
+            match &mut *thunk_state {
+                ThunkState::Evaluated(value) => {
+                    return Ok((State::ContinueWith(value.clone()), next));
+                }
+                ThunkState::Blackhole(location) => {
+                    return Err(RuntimeError::InfiniteLoop(*location));
+                }
+                ThunkState::Pending {
+                    env,
+                    expr,
+                    location,
+                } => {
+                    let location = *location;
+                    let pending = (
+                        std::mem::replace(env, ThunkEnv::Global),
+                        std::mem::replace(expr, Value::Null),
+                    );
+                    *thunk_state = ThunkState::Blackhole(location);
+                    pending
+                }
+            }
+        };
+        Ok((
+            State::Walk {
+                env: match env {
+                    ThunkEnv::Global => global_env.clone(),
+                    ThunkEnv::Local(env) => env,
+                },
+                expr,
+            },
+            Continuation::Update {
+                thunk,
+                next: Next::new(next),
+            },
+        ))
+    }
+    /// Delay evaluation of `expr` in `env` until it is needed.
+    fn suspend(env: &Env, expr: Value) -> Value {
+        match expr {
+            Value::Int(_) | Value::BigInt(_) | Value::Float(_) | Value::Str(_) | Value::Char(_) => {
+                expr
+            }
+            Value::Literal { value, .. } => *value,
+            // Share the existing binding rather than allocating a thunk which refers to it.
+            Value::Id(ref id) if env.has_symbol(id.name()) => {
+                env.get_symbol(id).cloned().expect("symbol is bound")
+            }
+            expr => {
+                let location = expr.location();
+                Value::thunk(env.clone(), expr, location)
+            }
+        }
+    }
+    /// Call `builtin` once it has been applied to enough arguments, forcing them first if the
+    /// builtin is strict.
+    fn apply_builtin(
+        builtin: Rc<Builtin>,
+        mut args: Vec<Value>,
+        next: Continuation,
+        global_env: &Env,
+    ) -> Result<(State, Continuation), RuntimeError> {
+        if args.len() < builtin.arity {
+            return Ok((
+                State::ContinueWith(Value::PartialApp { builtin, args }),
+                next,
+            ));
+        }
+        if !builtin.strict {
+            return call_builtin(&builtin, args, next, global_env);
+        }
+        args.reverse();
+        let arg = args.pop().expect("builtins take arguments");
+        force(
+            arg,
+            Continuation::ForceArgs {
+                builtin,
+                forced: Vec::new(),
+                pending: args,
+                next: Next::new(next),
+            },
+            global_env,
+        )
+    }
+    fn call_builtin(
+        builtin: &Builtin,
+        args: Vec<Value>,
+        next: Continuation,
+        global_env: &Env,
+    ) -> Result<(State, Continuation), RuntimeError> {
+        let result = (builtin.func)(args)?;
+        // Lazy builtins may return one of their arguments unevaluated.
+        force(result, next, global_env)
+    }
+    fn match_step(
+        step: MatchStep,
+        next: Continuation,
+        global_env: &Env,
+    ) -> Result<(State, Continuation), RuntimeError> {
+        match step {
+            MatchStep::Matched {
+                env,
+                expr,
+                fall_through,
+            } => {
+                let next = match fall_through {
+                    Some(matcher) => Continuation::FallThrough {
+                        matcher,
+                        next: Next::new(next),
+                    },
+                    None => next,
+                };
+                Ok((State::Walk { env, expr }, next))
+            }
+            MatchStep::Force {
+                matcher,
+                predicate,
+                value,
+            } => force(
+                value,
+                Continuation::ResumeMatch {
+                    matcher,
+                    predicate,
+                    next: Next::new(next),
+                },
+                global_env,
+            ),
+        }
+    }
     let global_env = env.clone();
     let mut state: State = State::Walk { env, expr };
     let mut continuation = Continuation::Done;
@@ -198,45 +498,227 @@ fn eval_loop(env: Env, expr: Value) -> Result<Value, RuntimeError> {
         match state {
             State::Walk { env, expr } => {
                 // The job of Walk is to ensure that the expression is in WHNF.
-                if expr.is_weak_head_normal_form() {
-                    state = State::ContinueWith(expr);
-                    continue;
-                }
                 match expr {
+                    Value::Int(_)
+                    | Value::BigInt(_)
+                    | Value::Float(_)
+                    | Value::Str(_)
+                    | Value::Char(_) => {
+                        state = State::ContinueWith(expr);
+                    }
+                    Value::Literal { value, .. } => {
+                        state = State::ContinueWith(*value);
+                    }
                     Value::Id(id) => {
-                        let expr = env
+                        let value = env
                             .get_symbol(&id)
                             .ok_or(RuntimeError::UnresolvedSymbol(id))?
                             .clone();
-                        state = State::Walk { env, expr };
+                        (state, continuation) = force(value, continuation, &global_env)?;
+                    }
+                    Value::Lambda { param, body } => {
+                        // Capture the defining env.
+                        state = State::ContinueWith(Value::Closure {
+                            param,
+                            body: Rc::new(*body),
+                            env,
+                        });
+                    }
+                    Value::Tuple { dims } => {
+                        state = State::ContinueWith(Value::Tuple {
+                            dims: dims.into_iter().map(|dim| suspend(&env, dim)).collect(),
+                        });
                     }
                     Value::Callsite { function, argument } => {
                         // Evaluate the callee, then apply the arguments to it.
+                        continuation = Continuation::ApplyTo {
+                            arg: suspend(&env, *argument),
+                            next: Next::new(continuation),
+                        };
                         state = State::Walk {
                             env,
                             expr: *function,
                         };
-                        // Chain the continuation.
-                        continuation = Continuation::ApplyTo {
-                            env: global_env.clone(),
-                            arg: *argument,
-                            next: Box::new(continuation),
+                    }
+                    Value::Match {
+                        subject,
+                        pattern_exprs,
+                        location,
+                    } => {
+                        // Evaluate the subject to WHNF, then try each clause against it.
+                        continuation = Continuation::Match {
+                            env: env.clone(),
+                            pattern_exprs,
+                            location,
+                            next: Next::new(continuation),
                         };
+                        state = State::Walk {
+                            env,
+                            expr: *subject,
+                        };
+                    }
+                    Value::Let { bindings, body } => {
+                        // Bind every name before filling in the thunks, so that the bindings can
+                        // refer to themselves and to each other.
+                        let mut env = env;
+                        let thunks: Vec<_> = bindings
+                            .iter()
+                            .map(|(name, _)| {
+                                let thunk =
+                                    Rc::new(RefCell::new(ThunkState::Blackhole(name.location())));
+                                env.add_symbol_mut(name.clone(), Value::Thunk(Rc::clone(&thunk)));
+                                thunk
+                            })
+                            .collect();
+                        for ((name, expr), thunk) in bindings.into_iter().zip(thunks) {
+                            *thunk.borrow_mut() = ThunkState::Pending {
+                                env: ThunkEnv::Local(env.clone()),
+                                expr,
+                                location: name.location(),
+                            };
+                        }
+                        state = State::Walk { env, expr: *body };
+                    }
+                    Value::Guarded {
+                        mut alternatives,
+                        location,
+                    } => {
+                        alternatives.reverse();
+                        let (condition, body) =
+                            alternatives.pop().expect("guards have an alternative");
+                        continuation = Continuation::Guard {
+                            env: env.clone(),
+                            body,
+                            alternatives,
+                            location,
+                            next: Next::new(continuation),
+                        };
+                        state = State::Walk {
+                            env,
+                            expr: condition,
+                        };
+                    }
+                    Value::NoField { ctor, field } => {
+                        return Err(RuntimeError::NoField { ctor, field });
                     }
-                    _ => todo!("handle {expr:?} in Walk {env:?}"),
+                    expr => return Err(RuntimeError::InvalidExpr(format!("{expr:?}"))),
                 }
             }
             State::ContinueWith(expr) => {
                 match continuation {
-                    Continuation::ApplyTo { mut env, arg, next } => {
-                        tracing::info!("applying {expr:?} to {arg:?}");
-                        let Value::Lambda { param, body } = expr else {
-                            panic!("expected lambda, got {expr:?}");
+                    Continuation::ApplyTo { arg, next } => {
+                        tracing::trace!("applying {expr:?} to {arg:?}");
+                        match expr {
+                            Value::Closure { param, body, env } => {
+                                // Apply the arguments to the function.
+                                state = State::Walk {
+                                    env: env.add_symbol(param, arg),
+                                    expr: (*body).clone(),
+                                };
+                                continuation = next.take();
+                            }
+                            Value::Builtin(builtin) => {
+                                (state, continuation) =
+                                    apply_builtin(builtin, vec![arg], next.take(), &global_env)?;
+                            }
+                            Value::PartialApp { builtin, mut args } => {
+                                args.push(arg);
+                                (state, continuation) =
+                                    apply_builtin(builtin, args, next.take(), &global_env)?;
+                            }
+                            _ => {
+                                return Err(RuntimeError::InvalidCallsite(format!(
+                                    "cannot apply {expr:?} to {arg:?}"
+                                )));
+                            }
+                        }
+                    }
+                    Continuation::ForceArgs {
+                        builtin,
+                        mut forced,
+                        mut pending,
+                        next,
+                    } => {
+                        forced.push(expr);
+                        (state, continuation) = match pending.pop() {
+                            Some(arg) => force(
+                                arg,
+                                Continuation::ForceArgs {
+                                    builtin,
+                                    forced,
+                                    pending,
+                                    next,
+                                },
+                                &global_env,
+                            )?,
+                            None => call_builtin(&builtin, forced, next.take(), &global_env)?,
                         };
-                        // Apply the arguments to the function.
-                        env.add_symbol_mut(param, arg);
-                        state = State::Walk { env, expr: *body };
-                        continuation = *next;
+                    }
+                    Continuation::Match {
+                        env,
+                        pattern_exprs,
+                        location,
+                        next,
+                    } => {
+                        let matcher = Matcher::new(env, expr, pattern_exprs, location)?;
+                        (state, continuation) =
+                            match_step(matcher.step()?, next.take(), &global_env)?;
+                    }
+                    Continuation::ResumeMatch {
+                        matcher,
+                        predicate,
+                        next,
+                    } => {
+                        (state, continuation) =
+                            match_step(matcher.resume(predicate, expr)?, next.take(), &global_env)?;
+                    }
+                    Continuation::Guard {
+                        env,
+                        body,
+                        mut alternatives,
+                        location,
+                        next,
+                    } => {
+                        let Value::Ctor { name, .. } = &expr else {
+                            return Err(RuntimeError::MatchTypeError(format!(
+                                "{location}: guard must be True or False, got {expr:?}"
+                            )));
+                        };
+                        if name.name() == "True" {
+                            // The clause can no longer fall through.
+                            continuation = match next.take() {
+                                Continuation::FallThrough { next, .. } => next.take(),
+                                next => next,
+                            };
+                            state = State::Walk { env, expr: body };
+                        } else if let Some((condition, body)) = alternatives.pop() {
+                            continuation = Continuation::Guard {
+                                env: env.clone(),
+                                body,
+                                alternatives,
+                                location,
+                                next,
+                            };
+                            state = State::Walk {
+                                env,
+                                expr: condition,
+                            };
+                        } else {
+                            let Continuation::FallThrough { matcher, next } = next.take() else {
+                                return Err(RuntimeError::NoGuard(location));
+                            };
+                            (state, continuation) =
+                                match_step(matcher.fall_through()?, next.take(), &global_env)?;
+                        }
+                    }
+                    Continuation::FallThrough { next, .. } => {
+                        state = State::ContinueWith(expr);
+                        continuation = next.take();
+                    }
+                    Continuation::Update { thunk, next } => {
+                        *thunk.borrow_mut() = ThunkState::Evaluated(expr.clone());
+                        state = State::ContinueWith(expr);
+                        continuation = next.take();
                     }
                     Continuation::Done => {
                         return Ok(expr);
@@ -251,10 +733,644 @@ test_each_path! { in "./tests" => test::test_pita_file }
 
 #[cfg(test)]
 mod test {
-    use crate::run_program;
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use crate::{
+        compile_program, docs,
+        env::Env,
+        eval_loop,
+        id::internal_id,
+        load_program,
+        location::Location,
+        run_main, run_program, run_source,
+        runtime::error::RuntimeError,
+        value::{PatternExpr, Predicate, ThunkState, Value},
+    };
 
     pub(crate) fn test_pita_file(filename: &std::path::Path) {
         let result = run_program(filename);
         assert!(result.is_ok(), "running {filename:?}: {result:?}");
     }
+
+    fn expect_error(content: &str, expected: &str) {
+        let result = run_source("<test>", content);
+        let Err(error) = result else {
+            panic!("expected an error containing {expected:?}, got {result:?}");
+        };
+        assert!(
+            error.to_string().contains(expected),
+            "expected an error containing {expected:?}, got {error}"
+        );
+    }
+
+    fn warnings(content: &str) -> Vec<String> {
+        let (_, warnings) = compile_program(Env::with_builtins(), "<test>", content).unwrap();
+        warnings.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_no_match_reports_subject_and_location() {
+        expect_error(
+            "main () = match 4 : 1 -> (2) 3 -> (4);",
+            "<test>:1:11: pita runtime error: no match for 4",
+        );
+    }
+
+    #[test]
+    fn test_self_dependent_value_is_an_infinite_loop() {
+        expect_error(
+            "x = x; main () = x;",
+            "<test>:1:1: pita runtime error: infinite loop",
+        );
+    }
+
+    #[test]
+    fn test_top_level_values_are_evaluated_once() {
+        let mut env = Env::with_builtins();
+        let ticks = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&ticks);
+        env.add_builtin("tick", "Int -> Int", 1, move |mut args| {
+            counter.set(counter.get() + 1);
+            Ok(args.remove(0))
+        });
+        let env = load_program(env, "<test>", "two = tick 2; main () = (+) two two;").unwrap();
+        assert!(matches!(run_main(env), Ok(Value::Int(4))));
+        assert_eq!(ticks.get(), 1);
+    }
+
+    #[test]
+    fn test_let_bindings_are_recursive() {
+        expect_error(
+            "main () = let x = 1 : let x = (+) x 1 : x;",
+            "<test>:1:27: pita runtime error: infinite loop",
+        );
+    }
+
+    #[test]
+    fn test_thunks_are_updated_with_their_whnf() {
+        let Value::Thunk(thunk) = Value::thunk(
+            Env::new(),
+            Value::Match {
+                subject: Box::new(Value::Int(1)),
+                pattern_exprs: vec![PatternExpr {
+                    predicate: Predicate::Irrefutable(internal_id("y")),
+                    expr: Value::Id(internal_id("y")),
+                }],
+                location: Location::unknown(),
+            },
+            Location::unknown(),
+        ) else {
+            unreachable!()
+        };
+        let env = Env::new().add_symbol(internal_id("x"), Value::Thunk(Rc::clone(&thunk)));
+        let result = eval_loop(env, Value::Id(internal_id("x")));
+        assert!(matches!(result, Ok(Value::Int(1))));
+        assert!(matches!(
+            &*RefCell::borrow(&thunk),
+            ThunkState::Evaluated(Value::Int(1))
+        ));
+    }
+
+    #[test]
+    fn test_long_chains_of_thunks_do_not_overflow_the_stack() {
+        let x = || Value::Id(internal_id("x"));
+        let env = |value| Env::new().add_symbol(internal_id("x"), value);
+        // Each thunk is `x`, where `x` is the thunk before it.
+        let chain = |first| {
+            (0..100_000).fold(first, |value, _| {
+                Value::thunk(env(value), x(), Location::unknown())
+            })
+        };
+        let result = eval_loop(env(chain(Value::Int(1))), x());
+        assert!(matches!(result, Ok(Value::Int(1))), "got {result:?}");
+        let missing = Value::thunk(Env::new(), x(), Location::unknown());
+        let result = eval_loop(env(chain(missing)), x());
+        assert!(
+            matches!(result, Err(RuntimeError::UnresolvedSymbol(_))),
+            "got {result:?}"
+        );
+        drop(chain(Value::Int(1)));
+        // Evaluated pairs, as the cells of a long list are.
+        let cells = (0..100_000).fold(Value::Int(0), |rest, _| {
+            Value::Thunk(Rc::new(RefCell::new(ThunkState::Evaluated(Value::Tuple {
+                dims: vec![Value::Int(1), rest],
+            }))))
+        });
+        drop(cells);
+    }
+
+    #[test]
+    fn test_lazy_builtins_receive_unevaluated_arguments() {
+        let mut env = Env::new();
+        env.add_lazy_builtin(
+            "first",
+            "a -> b -> a",
+            2,
+            |mut args| Ok(args.swap_remove(0)),
+        );
+        let call = |function, argument| Value::Callsite {
+            function: Box::new(function),
+            argument: Box::new(argument),
+        };
+        let expr = call(
+            call(Value::Id(internal_id("first")), Value::Int(1)),
+            Value::Id(internal_id("undefined")),
+        );
+        assert!(matches!(eval_loop(env, expr), Ok(Value::Int(1))));
+    }
+
+    #[test]
+    fn test_expressions_which_cannot_be_evaluated_are_errors() {
+        let result = eval_loop(Env::new(), Value::Null);
+        let Err(error) = result else {
+            panic!("expected an error, got {result:?}");
+        };
+        assert_eq!(
+            error.to_string(),
+            "pita runtime error: cannot evaluate null"
+        );
+    }
+
+    #[test]
+    fn test_applying_a_non_function_is_an_error() {
+        expect_error(
+            "main () = 1 2;",
+            "<test>:1:11: pita type error: cannot apply a value of type Int as a function",
+        );
+    }
+
+    #[test]
+    fn test_constructors_are_unique() {
+        expect_error(
+            "data Answer = Yes | No; data Vote = Yes | Abstain; main () = Yes;",
+            "<test>:1:37: constructor Yes is already defined",
+        );
+    }
+
+    #[test]
+    fn test_types_are_defined_once() {
+        expect_error(
+            "data T = A; data T = B Int; main () = 1;",
+            "<test>:1:18: type T is already defined at <test>:1:6",
+        );
+        expect_error(
+            "data Bool = Yes | No; main () = 1;",
+            "<test>:1:6: type Bool is already defined at <prelude>:",
+        );
+        expect_error(
+            "data Int = I; main () = 1;",
+            "<test>:1:6: pita type error: type Int is a builtin type",
+        );
+    }
+
+    #[test]
+    fn test_type_errors_cite_both_sites() {
+        expect_error(
+            "g x = match x : Nil -> (0) Cons 1 Nil -> (1); main () = g (Cons Unit Nil);",
+            "<test>:1:65: pita type error: expected Int, found Unit\n\
+             <test>:1:57: note: Int is required here",
+        );
+    }
+
+    #[test]
+    fn test_type_errors_prevent_running_the_program() {
+        expect_error(
+            "unused x = div x Unit; main () = 1;",
+            "<test>:1:18: pita type error: expected Int, found Unit",
+        );
+    }
+
+    #[test]
+    fn test_lambda_bound_variables_are_monomorphic() {
+        expect_error(
+            "both f = match f 1 : x -> (f Unit); id x = x; main () = both id;",
+            "expected Int, found Unit",
+        );
+    }
+
+    #[test]
+    fn test_infinite_types_are_rejected() {
+        expect_error(
+            "f x = x x; main () = 1;",
+            "<test>:1:9: pita type error: infinite type: a occurs in a -> b",
+        );
+    }
+
+    #[test]
+    fn test_main_takes_unit() {
+        expect_error("main = 3;", "expected () -> a, found Int");
+    }
+
+    #[test]
+    fn test_signatures_must_be_instances_of_inferred_types() {
+        expect_error(
+            "f :: a -> b -> a; f x y = y; main () = 1;",
+            "<test>:1:1: pita type error: f is declared as a -> b -> a, but its inferred type is \
+             a -> b -> b",
+        );
+    }
+
+    #[test]
+    fn test_signatures_restrict_uses() {
+        expect_error(
+            "idInt :: Int -> Int; idInt x = x; main () = idInt Unit;",
+            "<test>:1:51: pita type error: expected Int, found Unit",
+        );
+    }
+
+    #[test]
+    fn test_signatures_require_a_definition() {
+        expect_error(
+            "g :: Int; main () = 1;",
+            "<test>:1:1: the type signature for g lacks an accompanying definition",
+        );
+    }
+
+    #[test]
+    fn test_missing_cases_are_reported_with_an_example() {
+        assert_eq!(
+            warnings("len xs = match xs : Cons x rest -> (1); main () = len Nil;"),
+            ["<test>:1:10: pita warning: non-exhaustive match: Nil is not matched"]
+        );
+        assert_eq!(
+            warnings("f p = match p : (Nil, Nil) -> (1) (Cons a b, _) -> (2); main () = 1;"),
+            ["<test>:1:7: pita warning: non-exhaustive match: (Nil, Cons _ _) is not matched"]
+        );
+        assert_eq!(
+            warnings("f x = match x : 0 -> (1) 1 -> (3); main () = f 0;"),
+            ["<test>:1:7: pita warning: non-exhaustive match: 2 is not matched"]
+        );
+    }
+
+    #[test]
+    fn test_lambda_parameters_are_checked_for_exhaustiveness() {
+        assert_eq!(
+            warnings("f = \\(Cons x _) -> x; main () = f (Cons 1 Nil);"),
+            ["<test>:1:5: pita warning: non-exhaustive match: Nil is not matched"]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_clauses_are_reported() {
+        assert_eq!(
+            warnings("f x = match x : y -> (1) 2 -> (3); main () = f 0;"),
+            ["<test>:1:26: pita warning: unreachable clause: 2 is already matched"]
+        );
+        assert_eq!(
+            warnings("f b = match b : True -> (1) False -> (2) True -> (3); main () = f True;"),
+            ["<test>:1:42: pita warning: unreachable clause: True is already matched"]
+        );
+        assert_eq!(
+            warnings("f x = match x : y -> (1) z -> (2); main () = 1;"),
+            ["<test>:1:26: pita warning: unreachable clause: z is already matched"]
+        );
+        assert_eq!(
+            warnings("f (a:_) = a; f (b:c@(_:_)) = b; f [] = 0; main () = f [];"),
+            ["<test>:1:18: pita warning: unreachable clause: Cons b c@(Cons _ _) is already matched"]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_are_located() {
+        expect_error(
+            "main () = (1;",
+            "<test>:1:13: pita parse error: expected `)`",
+        );
+        expect_error(
+            "main () = 1\ng = 2;",
+            "<test>:2:3: pita parse error: expected `;`",
+        );
+        expect_error(
+            "main ( = 1;",
+            "<test>:1:6: pita parse error: expected `=` or `|`",
+        );
+        expect_error(
+            "main () = [1, 2;",
+            "<test>:1:16: pita parse error: expected `]`",
+        );
+    }
+
+    #[test]
+    fn test_invalid_escapes_are_located() {
+        expect_error(
+            "main () = \"a\\qb\";",
+            "<test>:1:13: pita parse error: invalid escape sequence",
+        );
+        expect_error(
+            "main () = '\\u{110000}';",
+            "<test>:1:12: pita parse error: invalid escape sequence",
+        );
+    }
+
+    #[test]
+    fn test_invalid_numbers_are_located() {
+        expect_error(
+            "main () = 0x;",
+            "<test>:1:11: pita parse error: invalid number",
+        );
+        expect_error(
+            "main () = 1__0;",
+            "<test>:1:11: pita parse error: invalid number",
+        );
+        expect_error(
+            "main () = 10_;",
+            "<test>:1:11: pita parse error: invalid number",
+        );
+        expect_error(
+            "main () = 1e400;",
+            "<test>:1:11: pita parse error: float is too large",
+        );
+        expect_error(
+            "f 0b = 1;\nmain () = f 0;",
+            "<test>:1:3: pita parse error: invalid number",
+        );
+    }
+
+    #[test]
+    fn test_splices_are_located() {
+        expect_error(
+            "main () = \"a ${1} b\";",
+            "<test>:1:16: pita type error: expected String, found Int",
+        );
+        expect_error("main () = \"a ${x +} b\";", "<test>:1:18: pita parse error");
+    }
+
+    #[test]
+    fn test_docs_are_looked_up_by_name() {
+        let source = include_str!("../tests/comments.pita");
+        let docs_of = |name| {
+            docs("<test>", source, name)
+                .unwrap()
+                .into_iter()
+                .map(|(location, doc)| format!("{location}: {doc}"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            docs_of("triangle"),
+            ["<test>:9:1: The sum of the numbers from 1 to n.\n\
+              Doc comments attach to the declaration which follows them."]
+        );
+        assert!(docs_of("main").is_empty());
+        assert_eq!(
+            docs_of("Ord"),
+            ["<prelude>:32:15: Instances define either `compare` or `<=`."]
+        );
+        assert_eq!(
+            docs_of("Ord List"),
+            ["<prelude>:130:19: Lists are ordered lexicographically."]
+        );
+    }
+
+    #[test]
+    fn test_lone_splices_are_strings() {
+        expect_error(
+            "main () = \"${1}\";",
+            "<test>:1:14: pita type error: expected String, found Int",
+        );
+        expect_error(
+            "f :: a -> a; f x = \"${x}\"; main () = f 1;",
+            "f is declared as a -> a, but its inferred type is String -> String",
+        );
+    }
+
+    #[test]
+    fn test_literal_patterns_are_located() {
+        assert_eq!(
+            warnings("f x = match x : -1 -> (1) - 1 -> (2) _ -> (3); main () = f 0;"),
+            ["<test>:1:27: pita warning: unreachable clause: -1 is already matched"]
+        );
+        assert_eq!(
+            warnings("f () = 1; f () = 2; main () = f ();"),
+            ["<test>:1:13: pita warning: unreachable clause: () is already matched"]
+        );
+        assert_eq!(
+            warnings("f s = match s : \"a\" -> (1) \"\" -> (2); main () = f \"a\";"),
+            ["<test>:1:7: pita warning: non-exhaustive match: \"aa\" is not matched"]
+        );
+    }
+
+    #[test]
+    fn test_record_fields_are_checked() {
+        let data = "data P = P { x :: Int, y :: Int }; ";
+        expect_error(
+            &format!("{data}main () = P {{ x = 1 }};"),
+            "<test>:1:46: field y of P is not given",
+        );
+        expect_error(
+            &format!("{data}main () = P {{ x = 1, y = 2, z = 3 }};"),
+            "<test>:1:64: P has no field z",
+        );
+        expect_error(
+            &format!("{data}main () = (P 1 2) {{ z = 3 }};"),
+            "<test>:1:54: no constructor has the fields z",
+        );
+        expect_error(
+            &format!("{data}f P {{ x = a, x = b }} = a; main () = f (P 1 2);"),
+            "<test>:1:49: field x is matched more than once",
+        );
+        expect_error(
+            &format!("{data}main () = (P 1 2).z;"),
+            "<test>:1:54: no constructor has the field z",
+        );
+    }
+
+    #[test]
+    fn test_partial_accessors_fail_at_runtime() {
+        let source = "data S = A { a :: Int } | B; main () = a B;";
+        assert!(warnings(source).is_empty());
+        expect_error(source, "<test>:1:14: pita runtime error: B has no field a");
+    }
+
+    #[test]
+    fn test_field_names_belong_to_one_type() {
+        expect_error(
+            "data P = P { name :: String }; data Q = Q { name :: Int }; main () = 1;",
+            "<test>:1:45: name is already a field of P at <test>:1:14",
+        );
+        expect_error(
+            "data P = P { name :: String }; name x = x; main () = 1;",
+            "<test>:1:32: name is already defined as a field at <test>:1:14",
+        );
+    }
+
+    #[test]
+    fn test_wildcards_bind_nothing() {
+        expect_error("f _ = _; main () = f 1;", "unresolved symbol: _");
+    }
+
+    #[test]
+    fn test_guarded_clauses_do_not_cover_their_pattern() {
+        assert_eq!(
+            warnings("f b = match b : True | b -> (1) False -> (2); main () = f True;"),
+            ["<test>:1:7: pita warning: non-exhaustive match: True is not matched"]
+        );
+        assert!(warnings(
+            "f b = match b : True | b -> (1) | otherwise -> (2) False -> (3); main () = f True;"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_failed_guards_without_fall_through_are_errors() {
+        expect_error("x | False = 1; main () = x;", "no guard is true");
+    }
+
+    #[test]
+    fn test_division_by_zero_is_an_error() {
+        expect_error("main () = 1 `div` 0;", "div: division by zero");
+        expect_error("main () = 1 `mod` (1 - 1);", "mod: division by zero");
+    }
+
+    #[test]
+    fn test_overflow_is_promoted() {
+        let result = run_source("<test>", "main () = -0x7fff_ffff_ffff_ffff - 2;");
+        let Ok(Value::BigInt(value)) = result else {
+            panic!("expected a big integer, got {result:?}");
+        };
+        assert_eq!(value.to_string(), "-9223372036854775809");
+    }
+
+    #[test]
+    fn test_exhaustive_matches_have_no_warnings() {
+        assert!(warnings(
+            "f xs = match xs : Nil -> (0) Cons 0 Nil -> (1) Cons n rest -> (2); main () = f Nil;"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_values_are_defined_once() {
+        expect_error(
+            "x = 1; x = 2; main () = x;",
+            "<test>:1:8: x is already defined at <test>:1:1",
+        );
+    }
+
+    #[test]
+    fn test_prelude_functions_are_not_extended() {
+        expect_error(
+            "not x = x; main () = not True;",
+            "<test>:1:1: not is already defined at <prelude>:17:1",
+        );
+        expect_error(
+            "concatMap f xs = []; main () = concatMap id [1];",
+            "<test>:1:1: concatMap is already defined at <prelude>:",
+        );
+    }
+
+    #[test]
+    fn test_values_and_clauses_do_not_mix() {
+        expect_error(
+            "f = 1; f 0 = 2; main () = f;",
+            "<test>:1:8: f is defined with arguments here, but as a value without arguments at \
+             <test>:1:1",
+        );
+        expect_error(
+            "f 0 = 2; f = 1; main () = f;",
+            "<test>:1:10: f is defined as a value without arguments here, but with arguments at \
+             <test>:1:1",
+        );
+    }
+
+    #[test]
+    fn test_clauses_take_the_same_number_of_arguments() {
+        expect_error(
+            "f 0 = 2; f x y = 1; main () = f 0;",
+            "<test>:1:10: this clause for f takes 2 arguments, but the clause at <test>:1:1 takes 1",
+        );
+    }
+
+    #[test]
+    fn test_interrupted_clauses_are_reported() {
+        assert_eq!(
+            warnings("f 0 = 1; g = 2; f n = n; main () = f g;"),
+            [
+                "<test>:1:17: pita warning: clauses for f are not contiguous; the previous \
+                 clause is at <test>:1:1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_associative_operators_do_not_chain() {
+        expect_error(
+            "main () = 1 == 2 == 3;",
+            "<test>:1:18: cannot mix == [infix 4] and == [infix 4] in the same infix expression",
+        );
+    }
+
+    #[test]
+    fn test_operators_of_opposite_associativity_do_not_mix() {
+        expect_error(
+            "infixr 6 <+>; x <+> y = x; main () = 1 + 2 <+> 3;",
+            "<test>:1:44: cannot mix + [infixl 6] and <+> [infixr 6] in the same infix expression",
+        );
+    }
+
+    #[test]
+    fn test_negation_binds_like_minus() {
+        expect_error(
+            "infixr 6 <+>; x <+> y = x; main () = - 1 <+> 2;",
+            "<test>:1:42: cannot mix - [infixl 6] and <+> [infixr 6] in the same infix expression",
+        );
+    }
+
+    #[test]
+    fn test_fixities_are_declared_once() {
+        expect_error(
+            "infixl 6 <+>; infixr 5 <+>; x <+> y = x; main () = 1;",
+            "<test>:1:24: the fixity of <+> is already declared at <test>:1:10",
+        );
+    }
+
+    #[test]
+    fn test_overloaded_uses_need_an_instance() {
+        expect_error(
+            "main () = show (\\x -> x);",
+            "<test>:1:11: pita type error: no instance for Show (a -> a)",
+        );
+        expect_error(
+            "data T = T;\ninstance Ord T where compare _ _ = EQ;\nmain () = 1;",
+            "<test>:2:10: pita type error: no instance for Eq T",
+        );
+    }
+
+    #[test]
+    fn test_signatures_declare_their_constraints() {
+        expect_error(
+            "f :: a -> String;\nf x = show x;\nmain () = f 1;",
+            "<test>:2:7: pita type error: no instance for Show a",
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_constraints_are_rejected() {
+        expect_error(
+            "main () = show (fromInt 1);",
+            "<test>:1:17: pita type error: ambiguous type",
+        );
+    }
+
+    #[test]
+    fn test_instances_define_every_method_without_a_default() {
+        expect_error(
+            "data T = T;\ninstance Num T where (+) a b = a;\nmain () = 1;",
+            "<test>:2:10: the instance of Num for T lacks a definition of -",
+        );
+        expect_error(
+            "data T = T;\ninstance Eq T where (<) a b = True;\nmain () = 1;",
+            "<test>:2:22: < is not a method of class Eq",
+        );
+    }
+
+    #[test]
+    fn test_closures_do_not_see_caller_bindings() {
+        expect_error(
+            "getx y = x; usex x = getx 0; main () = usex 1;",
+            "unresolved symbol: x",
+        );
+    }
 }
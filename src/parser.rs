@@ -1,40 +1,440 @@
 #![allow(dead_code)]
-use std::str::FromStr;
+use std::{cmp::Ordering, str::FromStr};
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while, take_while1},
-    character::complete::{char, digit1, multispace0},
-    combinator::{cut, map, map_res, recognize},
-    error::ParseError,
-    multi::{many0, many1, separated_list0},
-    sequence::{delimited, pair, terminated},
+    bytes::complete::{
+        is_not, tag, tag_no_case, take, take_until, take_while, take_while1, take_while_m_n,
+    },
+    character::complete::{char, digit1, multispace0, one_of, satisfy},
+    combinator::{
+        all_consuming, consumed, cut, map, map_opt, map_res, not, opt, peek, recognize, success,
+        value, verify,
+    },
+    error::{ErrorKind, FromExternalError, ParseError},
+    multi::{many0, many1, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated},
     Parser,
 };
 use nom_locate::LocatedSpan;
+use num_bigint::BigInt;
+use num_traits::Num;
 
 use crate::{
-    error::PitaError,
-    id::{internal_ctor_id, internal_id, parse_id, Id, IdImpl},
+    decl::{build_bindings, build_lambda},
+    error::{error, PitaError},
+    id::{
+        ctor_id_at, gensym, id_at, internal_ctor_id, parse_id, prelude_alias, CtorIdImpl, Id,
+        IdImpl,
+    },
+    location::{Location, LocationFilename},
     token::Token,
-    value::{Decl, PatternExpr, Predicate, Value},
+    typecheck::types::tuple_name,
+    value::{
+        Assoc, ClassDecl, Constraint, CtorDecl, DataDecl, Decl, FixityDecl, InstanceDecl, Item,
+        PatternExpr, Predicate, Signature, TypeExpr, Value,
+    },
 };
 
-type IResult<'a, O> = nom::IResult<Span<'a>, O>;
-pub type Span<'a> = LocatedSpan<&'a str, &'static str>;
+type IResult<'a, O> = nom::IResult<Span<'a>, O, SyntaxError<'a>>;
+pub type Span<'a> = LocatedSpan<&'a str, SpanExtra>;
+
+/// A failure to parse. Of the alternatives which fail, the one which got furthest into the input
+/// is kept, since it is the most likely to have been what was meant.
+#[derive(Debug)]
+pub(crate) struct SyntaxError<'a> {
+    input: Span<'a>,
+    kind: ErrorKind,
+    /// The characters which any of the alternatives which got this far expected next.
+    expected: Vec<char>,
+}
+
+impl<'a> ParseError<Span<'a>> for SyntaxError<'a> {
+    fn from_error_kind(input: Span<'a>, kind: ErrorKind) -> Self {
+        Self {
+            input,
+            kind,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_: Span<'a>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: Span<'a>, c: char) -> Self {
+        Self {
+            input,
+            kind: ErrorKind::Char,
+            expected: vec![c],
+        }
+    }
+
+    fn or(mut self, mut other: Self) -> Self {
+        match self
+            .input
+            .location_offset()
+            .cmp(&other.input.location_offset())
+        {
+            Ordering::Less => other,
+            Ordering::Greater => self,
+            Ordering::Equal => {
+                for c in other.expected.drain(..) {
+                    if !self.expected.contains(&c) {
+                        self.expected.push(c);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+impl<'a, E> FromExternalError<Span<'a>, E> for SyntaxError<'a> {
+    fn from_external_error(input: Span<'a>, kind: ErrorKind, _: E) -> Self {
+        Self::from_error_kind(input, kind)
+    }
+}
+
+/// The state carried along with the input: the file being parsed, the innermost layout block
+/// which the input is in, the column of the top-level item which it is in, and whether `:` is the
+/// cons operator.
+#[derive(Debug, Clone, Copy)]
+pub struct SpanExtra {
+    pub filename: LocationFilename,
+    block: Block,
+    /// The column at which the enclosing top-level item starts, or 0 outside of any item.
+    item_column: usize,
+    /// `:` ends the subject of a `match` and the bindings of a `let`, so it is only the cons
+    /// operator within them once it is nested in brackets.
+    cons: bool,
+}
+
+/// An implicit layout block, such as the lines of a `do` block which is written without braces.
+/// Each item of the block starts on a new line at `column`, and any other token of the item must
+/// be indented further. Tokens which are not are offside, and end the item.
+#[derive(Debug, Clone, Copy, Default)]
+struct Block {
+    /// The column of each item, or 0 outside of any layout block.
+    column: usize,
+    /// The offset of the first token of the current item.
+    item_start: usize,
+}
+
+pub fn new_span(content: &str, filename: LocationFilename) -> Span<'_> {
+    Span::new_extra(
+        content,
+        SpanExtra {
+            filename,
+            block: Block::default(),
+            item_column: 0,
+            cons: true,
+        },
+    )
+}
 
 fn is_identifier_char(c: char) -> bool {
-    c.is_alphanumeric() || "_!@$%^&*+=<>|".contains(c)
+    c.is_alphanumeric() || c == '_'
 }
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
-/// trailing whitespace, returning the output of `inner`.
+/// trailing whitespace, returning the output of `inner`. `inner` only matches tokens which are not
+/// offside.
 pub fn ws<'a, O, E: ParseError<Span<'a>>, F>(
     inner: F,
 ) -> impl Parser<Span<'a>, Output = O, Error = E>
 where
     F: Parser<Span<'a>, Output = O, Error = E>,
 {
-    delimited(multispace0, inner, multispace0)
+    delimited(pair(blank, onside), inner, blank_before_doc)
+}
+
+/// Skip whitespace and comments. Comments are either `--` to the end of the line, or enclosed in
+/// `{-` and `-}`, which may be nested.
+fn blank<'a, E: ParseError<Span<'a>>>(input: Span<'a>) -> nom::IResult<Span<'a>, (), E> {
+    skip_blank(input, true)
+}
+
+/// Like `blank`, but stop at a doc comment which is attached to the item which follows it.
+fn blank_before_doc<'a, E: ParseError<Span<'a>>>(input: Span<'a>) -> nom::IResult<Span<'a>, (), E> {
+    skip_blank(input, false)
+}
+
+fn skip_blank<'a, E: ParseError<Span<'a>>>(
+    mut input: Span<'a>,
+    skip_docs: bool,
+) -> nom::IResult<Span<'a>, (), E> {
+    loop {
+        (input, _) = multispace0(input)?;
+        let comment_len = if input.starts_with(DOC_COMMENT) {
+            if !skip_docs && is_attached_doc(&input) {
+                return Ok((input, ()));
+            }
+            line_len(&input)
+        } else if is_line_comment(&input) {
+            line_len(&input)
+        } else if input.starts_with("{-") {
+            block_comment_len(&input)
+                .ok_or_else(|| nom::Err::Failure(E::from_error_kind(input, ErrorKind::TakeUntil)))?
+        } else {
+            return Ok((input, ()));
+        };
+        (input, _) = take(comment_len).parse(input)?;
+    }
+}
+
+const DOC_COMMENT: &str = "--|";
+
+/// Whether the doc comment at the start of `input` is attached to an item: it begins its line,
+/// and each following line is another doc comment, up to the line on which the item starts.
+/// Any other doc comment is a plain comment.
+fn is_attached_doc(input: &Span) -> bool {
+    let before = &input.get_line_beginning()[..input.get_column() - 1];
+    if !before.iter().all(u8::is_ascii_whitespace) {
+        return false;
+    }
+    let mut rest: &str = input.fragment();
+    loop {
+        let Some(newline) = rest.find('\n') else {
+            return false;
+        };
+        rest = rest[newline + 1..].trim_start_matches([' ', '\t', '\r']);
+        if !rest.starts_with(DOC_COMMENT) {
+            return !rest.is_empty()
+                && !rest.starts_with('\n')
+                && !is_line_comment(rest)
+                && !rest.starts_with("{-");
+        }
+    }
+}
+
+/// Whether `input` starts with a line comment: two or more dashes which are not part of a longer
+/// operator such as `-->`.
+fn is_line_comment(input: &str) -> bool {
+    input.starts_with("--")
+        && !input
+            .trim_start_matches('-')
+            .starts_with(|c: char| is_operator_char(c) && c != '-')
+}
+
+fn line_len(input: &str) -> usize {
+    input.find('\n').unwrap_or(input.len())
+}
+
+/// The length of the block comment at the start of `input`, if it is terminated.
+fn block_comment_len(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut offset = 0;
+    while offset < input.len() {
+        let rest = &input[offset..];
+        if rest.starts_with("{-") {
+            depth += 1;
+            offset += 2;
+        } else if rest.starts_with("-}") {
+            depth -= 1;
+            offset += 2;
+            if depth == 0 {
+                return Some(offset);
+            }
+        } else {
+            offset += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    None
+}
+
+/// Parse the doc comments before a declaration, such as `--| Returns the length of a list.`, and
+/// join their text into lines.
+fn doc_comment_parser(input: Span) -> IResult<Option<String>> {
+    map(
+        preceded(
+            blank_before_doc,
+            many0(terminated(
+                preceded(tag(DOC_COMMENT), take_while(|c| c != '\n')),
+                blank_before_doc,
+            )),
+        ),
+        |lines: Vec<Span>| {
+            (!lines.is_empty()).then(|| {
+                lines
+                    .iter()
+                    .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        },
+    )
+    .parse(input)
+}
+
+/// Fail if the next token is offside: if it is within a layout block, but is neither the first
+/// token of the current item nor indented further than the items of the block.
+fn onside<'a, E: ParseError<Span<'a>>>(input: Span<'a>) -> nom::IResult<Span<'a>, (), E> {
+    let block = input.extra.block;
+    if input.naive_get_utf8_column() <= block.column && input.location_offset() != block.item_start
+    {
+        Err(nom::Err::Error(E::from_error_kind(
+            input,
+            ErrorKind::Verify,
+        )))
+    } else {
+        Ok((input, ()))
+    }
+}
+
+/// A combinator that runs `inner` within `block`, restoring the enclosing block afterwards.
+fn within<'a, O, F>(
+    block: Block,
+    mut inner: F,
+) -> impl Parser<Span<'a>, Output = O, Error = SyntaxError<'a>>
+where
+    F: Parser<Span<'a>, Output = O, Error = SyntaxError<'a>>,
+{
+    move |input: Span<'a>| {
+        let outer = input.extra.block;
+        let (rest, output) = inner.parse(input.map_extra(|extra| SpanExtra { block, ..extra }))?;
+        Ok((
+            rest.map_extra(|extra| SpanExtra {
+                block: outer,
+                ..extra
+            }),
+            output,
+        ))
+    }
+}
+
+/// A combinator that runs `inner` with `:` as the cons operator or not, restoring the enclosing
+/// setting afterwards.
+fn with_cons<'a, O, F>(
+    cons: bool,
+    mut inner: F,
+) -> impl Parser<Span<'a>, Output = O, Error = SyntaxError<'a>>
+where
+    F: Parser<Span<'a>, Output = O, Error = SyntaxError<'a>>,
+{
+    move |input: Span<'a>| {
+        let outer = input.extra.cons;
+        let (rest, output) = inner.parse(input.map_extra(|extra| SpanExtra { cons, ..extra }))?;
+        Ok((
+            rest.map_extra(|extra| SpanExtra {
+                cons: outer,
+                ..extra
+            }),
+            output,
+        ))
+    }
+}
+
+/// Where `;` may separate the items of a layout block, besides each item starting a new line.
+#[derive(Clone, Copy)]
+enum Semicolons {
+    Never,
+    /// Before an item on the same line, as in `do a; b`.
+    SameLine,
+    /// Before an item anywhere. This suits blocks which are always followed by a terminator, as
+    /// `let` bindings are by `:`, since the block cannot swallow what follows it.
+    Anywhere,
+}
+
+/// A combinator that parses one or more `item`s laid out in a block, whose column is that of the
+/// first item. The first item may be anywhere, even to the left of an enclosing block, so that
+/// nested blocks need not be indented past the start of a long line.
+fn layout_block<'a, O>(
+    item: fn(Span<'a>) -> IResult<'a, O>,
+    semicolons: Semicolons,
+) -> impl Parser<Span<'a>, Output = Vec<O>, Error = SyntaxError<'a>> {
+    move |input: Span<'a>| {
+        let (start, _) = blank(input)?;
+        let column = start.naive_get_utf8_column();
+        let mut items = Vec::new();
+        let mut rest = start;
+        let mut next = Some(start);
+        while let Some(start) = next {
+            // An item which follows a `;` may start to the left of the block, in which case the
+            // rest of the item need only be indented past its own start.
+            let block = Block {
+                column: column.min(start.naive_get_utf8_column()),
+                item_start: start.location_offset(),
+            };
+            match within(block, item).parse(start) {
+                Ok((after, output)) => {
+                    items.push(output);
+                    rest = after;
+                }
+                // A token at the block's column which does not start an item ends the block.
+                Err(nom::Err::Error(_)) if !items.is_empty() => break,
+                Err(error) => return Err(error),
+            }
+            next = next_item(rest, column, semicolons);
+        }
+        Ok((rest, items))
+    }
+}
+
+/// Find the start of the item of a layout block at `column` which follows `input`, if any. A `;`
+/// does not continue the block when a new top-level item follows it, so several items of a block
+/// which starts on the first line of a top-level item must be written in braces.
+fn next_item(input: Span, column: usize, semicolons: Semicolons) -> Option<Span> {
+    let at_column = |span: Span| span.naive_get_utf8_column() == column && !span.is_empty();
+    let (input, _) = blank::<SyntaxError>(input).ok()?;
+    let after_semicolon = preceded(char::<_, SyntaxError>(';'), blank)
+        .parse(input)
+        .ok()
+        .map(|(after, _)| after);
+    match (semicolons, after_semicolon) {
+        (Semicolons::SameLine, Some(after))
+            if (after.location_line() == input.location_line() || at_column(after))
+                && !starts_item(after) =>
+        {
+            Some(after)
+        }
+        (Semicolons::Anywhere, Some(after)) => Some(after),
+        _ => at_column(input).then_some(input),
+    }
+}
+
+/// Whether `input` starts a new top-level item: it parses as one, and its line is not indented
+/// past the start of the enclosing top-level item.
+fn starts_item(input: Span) -> bool {
+    let indentation = input
+        .get_line_beginning()
+        .iter()
+        .take_while(|c| c.is_ascii_whitespace())
+        .count();
+    indentation < input.extra.item_column
+        && within(Block::default(), with_cons(true, item_parser))
+            .parse(input)
+            .is_ok()
+}
+
+/// A combinator that parses `{ item; item; ... }`, where the items are not laid out.
+fn braced_block<'a, O>(
+    item: fn(Span<'a>) -> IResult<'a, O>,
+) -> impl Parser<Span<'a>, Output = Vec<O>, Error = SyntaxError<'a>> {
+    preceded(
+        ws(char('{')),
+        within(
+            Block::default(),
+            terminated(
+                terminated(separated_list1(ws(char(';')), item), opt(ws(char(';')))),
+                ws(char('}')),
+            ),
+        ),
+    )
+}
+
+/// Parse the keyword `kw`, as long as it is not just the start of a longer identifier.
+fn keyword<'a>(
+    kw: &'static str,
+) -> impl Parser<Span<'a>, Output = Span<'a>, Error = SyntaxError<'a>> {
+    terminated(tag(kw), not(satisfy(is_identifier_char)))
+}
+
+/// A combinator that records the `Location` at which `inner` starts matching.
+fn located<'a, O, F>(inner: F) -> impl Parser<Span<'a>, Output = Location, Error = F::Error>
+where
+    F: Parser<Span<'a>, Output = O>,
+{
+    map(consumed(inner), |(span, _)| Location::from(&span))
 }
 
 fn identifier(input: Span) -> IResult<Span> {
@@ -49,112 +449,610 @@ fn id_parser(input: Span) -> IResult<Id> {
     map_res(map(ws(identifier), Token::from), parse_id::<IdImpl>).parse(input)
 }
 
+fn is_operator_char(c: char) -> bool {
+    "!#$%&*+./<=>?@\\^|-~:".contains(c)
+}
+
+/// Parse an operator symbol such as `+` or `>>=`. Reserved symbols such as `=` and `->` are
+/// rejected, since they are keywords.
+fn operator_symbol(input: Span) -> IResult<Id> {
+    map_res(
+        map(take_while1(is_operator_char), Token::from),
+        parse_id::<IdImpl>,
+    )
+    .parse(input)
+}
+
+/// Parse an operator in infix position: either a symbol, a named function in backticks such as
+/// `` `div` ``, or `:`.
+fn infix_operator_parser(input: Span) -> IResult<Id> {
+    ws(alt((
+        operator_symbol,
+        delimited(
+            char('`'),
+            map_res(map(identifier, Token::from), parse_id::<IdImpl>),
+            char('`'),
+        ),
+        cons_operator,
+    )))
+    .parse(input)
+}
+
+/// Parse `:`, which is `Cons` written infix, unless `:` ends the expression being parsed.
+fn cons_operator(input: Span) -> IResult<Id> {
+    if !input.extra.cons {
+        return Err(nom::Err::Error(SyntaxError::from_error_kind(
+            input,
+            ErrorKind::Verify,
+        )));
+    }
+    map(
+        located(terminated(char(':'), not(satisfy(is_operator_char)))),
+        |location| ctor_id_at("Cons", location),
+    )
+    .parse(input)
+}
+
+/// Parse a parenthesized operator such as `(+)` as a reference to the function it names.
+fn operator_id_parser(input: Span) -> IResult<Id> {
+    delimited(ws(char('(')), ws(operator_symbol), ws(char(')'))).parse(input)
+}
+
+/// Parse the name being defined by a binding or signature, which may be a parenthesized
+/// operator.
+fn binder_parser(input: Span) -> IResult<Id> {
+    alt((operator_id_parser, id_parser)).parse(input)
+}
+
 fn number_parser(input: Span) -> IResult<Value> {
-    map_res(ws(digit1), |x| i64::from_str(x.fragment()).map(Value::Int)).parse(input)
+    ws(map(
+        consumed(alt((map(float_literal, Value::Float), integer_literal))),
+        |(span, value)| Value::Literal {
+            value: Box::new(value),
+            location: Location::from(&span),
+        },
+    ))
+    .parse(input)
+}
+
+/// Parse digits in `radix`, which may be separated by underscores as in `1_000_000`. Digits which
+/// end in an underscore, or which have two in a row, are an invalid number.
+fn digits<'a>(radix: u32) -> impl Parser<Span<'a>, Output = String, Error = SyntaxError<'a>> {
+    move |input: Span<'a>| {
+        let (rest, digits) = recognize(pair(
+            satisfy(move |c| c.is_digit(radix)),
+            take_while(move |c: char| c.is_digit(radix) || c == '_'),
+        ))
+        .parse(input)?;
+        if digits.ends_with('_') || digits.contains("__") {
+            return Err(invalid_number(input, ErrorKind::Digit));
+        }
+        Ok((rest, digits.replace('_', "")))
+    }
+}
+
+/// An invalid number which starts at `input`. It cannot be anything else, so this is a failure.
+fn invalid_number(input: Span, kind: ErrorKind) -> nom::Err<SyntaxError> {
+    nom::Err::Failure(SyntaxError::from_error_kind(input, kind))
 }
 
+/// Parse an integer in decimal, or in hex, octal or binary after `0x`, `0o` or `0b`. Integers
+/// which do not fit in an `i64` are big.
+fn integer_literal(input: Span) -> IResult<Value> {
+    let (rest, radix) = alt((
+        value(16, tag_no_case("0x")),
+        value(8, tag_no_case("0o")),
+        value(2, tag_no_case("0b")),
+        success(10),
+    ))
+    .parse(input)?;
+    let (rest, digits) = match digits(radix).parse(rest) {
+        // A prefix must be followed by digits.
+        Err(nom::Err::Error(_)) if radix != 10 => {
+            return Err(invalid_number(input, ErrorKind::Digit))
+        }
+        result => result?,
+    };
+    let value = BigInt::from_str_radix(&digits, radix).expect("the digits are in the radix");
+    Ok((rest, Value::integer(value)))
+}
+
+/// Parse a float with a fraction, an exponent or both, such as `1.5`, `1e9` or `2.5e-3`. Floats
+/// which are too large to represent are invalid, rather than infinite.
+fn float_literal(input: Span) -> IResult<f64> {
+    let exponent = || (one_of("eE"), opt(one_of("+-")), digits(10));
+    let (rest, text) = recognize((
+        digits(10),
+        alt((
+            recognize((char('.'), digits(10), opt(exponent()))),
+            recognize(exponent()),
+        )),
+    ))
+    .parse(input)?;
+    match f64::from_str(&text.replace('_', "")) {
+        Ok(value) if value.is_finite() => Ok((rest, value)),
+        _ => Err(invalid_number(input, ErrorKind::Float)),
+    }
+}
+
+/// Parse a string literal as an expression. Expressions spliced into a quoted string with
+/// `${...}` are appended to the text around them with `<>`.
 fn string_literal_parser(input: Span) -> IResult<Value> {
-    map(string_literal, Value::Str).parse(input)
+    preceded(
+        blank,
+        alt((
+            map_opt(ws(quoted_string_parts), |parts| {
+                parts
+                    .iter()
+                    .any(|part| matches!(part, StringPart::Splice(..)))
+                    .then(|| interpolate(parts))
+            }),
+            map(consumed(string_literal), |(span, text)| Value::Literal {
+                value: Box::new(Value::Str(text)),
+                location: Location::from(&span),
+            }),
+        )),
+    )
+    .parse(input)
+}
+
+/// A run of text within a quoted string, located at its start, or an expression spliced into
+/// it, located at its `${`.
+enum StringPart {
+    Text(String, Location),
+    Splice(Value, Location),
+}
+
+/// Append the parts of an interpolated string together from the right. Each `<>` is located at
+/// the part which it appends to the rest. A lone splice is appended to `""`, so that every
+/// interpolated string is a `String`.
+fn interpolate(mut parts: Vec<StringPart>) -> Value {
+    if let [StringPart::Splice(_, location)] = parts[..] {
+        parts.insert(0, StringPart::Text(String::new(), location));
+    }
+    parts
+        .into_iter()
+        .map(|part| match part {
+            StringPart::Text(text, location) => (
+                Value::Literal {
+                    value: Box::new(Value::Str(text)),
+                    location,
+                },
+                location,
+            ),
+            StringPart::Splice(expr, location) => (expr, location),
+        })
+        .rev()
+        .reduce(|(tail, _), (head, location)| {
+            let append = Value::Id(id_at("<>", location));
+            (apply_terms(vec![append, head, tail]), location)
+        })
+        .expect("interpolated strings have a splice")
+        .0
 }
 
+/// Parse a string literal: a quoted string such as `"a\tb"`, a raw string such as `r"C:\dir"`
+/// or a triple-quoted multi-line string.
 fn string_literal(input: Span) -> IResult<String> {
-    ws(delimited(
-        char('"'),
-        map(
-            many0(alt((
-                map(tag("\\\""), |_| '"'),
-                map(tag("\\n"), |_| '\n'),
-                map(tag("\\t"), |_| '\t'),
-                map(tag("\\r"), |_| '\r'),
-                map(take_while1(|c| c != '"' && c != '\\'), |s: Span| {
-                    s.chars().next().unwrap()
-                }),
-            ))),
-            |chars| chars.into_iter().collect(),
+    ws(alt((
+        multiline_string_literal,
+        raw_string_literal,
+        quoted_string_literal,
+    )))
+    .parse(input)
+}
+
+/// Parse a quoted string which has no splices.
+fn quoted_string_literal(input: Span) -> IResult<String> {
+    map_opt(quoted_string_parts, |parts| {
+        parts
+            .into_iter()
+            .map(|part| match part {
+                StringPart::Text(text, _) => Some(text),
+                StringPart::Splice(..) => None,
+            })
+            .collect()
+    })
+    .parse(input)
+}
+
+/// Parse a quoted string into runs of text and splices.
+/// A `$` only starts a splice when it is followed by `{`, and `\$` is always a `$`.
+fn quoted_string_parts(input: Span) -> IResult<Vec<StringPart>> {
+    let text = map(
+        consumed(many1(alt((
+            map(escape, String::from),
+            map(is_not("\"\\$"), |run: Span| run.fragment().to_string()),
+            map(terminated(tag("$"), not(char('{'))), |_| "$".to_string()),
+        )))),
+        |(span, fragments)| StringPart::Text(fragments.concat(), Location::from(&span)),
+    );
+    let splice = map(
+        pair(
+            located(tag("${")),
+            cut(terminated(with_cons(true, expr_parser), char('}'))),
+        ),
+        |(location, expr)| StringPart::Splice(expr, location),
+    );
+    delimited(char('"'), many0(alt((text, splice))), char('"')).parse(input)
+}
+
+/// Parse a raw string such as `r"C:\dir"` or `r#"say "hi""#`, in which backslashes are not
+/// escapes. It ends at a quote followed by as many `#`s as preceded its opening quote.
+fn raw_string_literal(input: Span) -> IResult<String> {
+    let (input, hashes) = delimited(char('r'), take_while(|c| c == '#'), char('"')).parse(input)?;
+    let terminator = format!("\"{}", hashes.fragment());
+    let (input, text) = cut(terminated(
+        take_until(terminator.as_str()),
+        tag(terminator.as_str()),
+    ))
+    .parse(input)?;
+    Ok((input, text.fragment().to_string()))
+}
+
+/// Parse a string between `"""`s, which may span several lines. The line break after the opening
+/// quotes and the indentation before the closing quotes are dropped, along with the indentation
+/// which all of the other non-blank lines have in common.
+fn multiline_string_literal(input: Span) -> IResult<String> {
+    let (input, body) = preceded(
+        tag("\"\"\""),
+        cut(terminated(take_until("\"\"\""), tag("\"\"\""))),
+    )
+    .parse(input)?;
+    let raw_lines: Vec<&str> = body.fragment().split('\n').collect();
+    let indent = raw_lines[1..]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+        .unwrap_or(0);
+    let line = |indent| {
+        preceded(
+            take_while_m_n(0, indent, |c: char| c == ' ' || c == '\t'),
+            map(
+                many0(alt((
+                    map(escape, String::from),
+                    map(is_not("\\\n"), |run: Span| run.fragment().to_string()),
+                ))),
+                |fragments| fragments.concat(),
+            ),
+        )
+    };
+    let (_, (first, rest)) =
+        all_consuming(pair(line(0), many0(preceded(char('\n'), line(indent))))).parse(body)?;
+    let mut lines = vec![first];
+    lines.extend(rest);
+    if raw_lines.len() > 1 && raw_lines[raw_lines.len() - 1].trim().is_empty() {
+        lines.pop();
+    }
+    if raw_lines[0].trim().is_empty() {
+        lines.remove(0);
+    }
+    Ok((input, lines.join("\n")))
+}
+
+/// Parse an escape sequence such as `\n`, `\x41` or `\u{1F600}`. A backslash which does not start
+/// a known escape is a failure located at the backslash.
+fn escape(input: Span) -> IResult<char> {
+    let (rest, _) = char('\\').parse(input)?;
+    let escaped: IResult<char> = alt((
+        value('"', char('"')),
+        value('\'', char('\'')),
+        value('\\', char('\\')),
+        value('$', char('$')),
+        value('\n', char('n')),
+        value('\t', char('t')),
+        value('\r', char('r')),
+        value('\0', char('0')),
+        map_opt(
+            preceded(
+                char('x'),
+                take_while_m_n(2, 2, |c: char| c.is_ascii_hexdigit()),
+            ),
+            |digits: Span| {
+                u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(u8::is_ascii)
+                    .map(char::from)
+            },
+        ),
+        map_opt(
+            delimited(
+                tag("u{"),
+                take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit()),
+                char('}'),
+            ),
+            |digits: Span| {
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            },
         ),
-        char('"'),
     ))
+    .parse(rest);
+    escaped.map_err(|_| nom::Err::Failure(ParseError::from_error_kind(input, ErrorKind::Escaped)))
+}
+
+fn char_literal_parser(input: Span) -> IResult<Value> {
+    map(consumed(char_literal), |(span, c)| Value::Literal {
+        value: Box::new(Value::Char(c)),
+        location: Location::from(&span),
+    })
+    .parse(input)
+}
+
+/// Parse a character such as `'a'` or `'\n'`.
+fn char_literal(input: Span) -> IResult<char> {
+    delimited(
+        char('\''),
+        alt((escape, satisfy(|c| c != '\'' && c != '\\' && c != '\n'))),
+        char('\''),
+    )
     .parse(input)
 }
 
+fn ctor_id_parser(input: Span) -> IResult<Id> {
+    map_res(map(ws(identifier), Token::from), parse_id::<CtorIdImpl>).parse(input)
+}
+
 fn tuple_predicate_parser(input: Span) -> IResult<Predicate> {
     map(
-        delimited(
-            ws(char('(')),
+        (
+            ws(located(char('('))),
             separated_list0(ws(char(',')), predicate_parser),
             ws(char(')')),
         ),
-        Predicate::Tuple,
+        |(location, mut predicates, _)| {
+            // A single parenthesized predicate is just grouping.
+            if predicates.len() == 1 {
+                predicates.remove(0)
+            } else {
+                Predicate::Tuple(predicates, location)
+            }
+        },
     )
     .parse(input)
 }
 
-fn ctor_predicate_parser(input: Span) -> IResult<Predicate> {
-    ws(map(
-        pair(id_parser, many0(predicate_parser)),
-        |(ctor, preds)| Predicate::Ctor(ctor, preds),
-    ))
+/// Parse `[a, b, c]`, which matches a list of exactly that many elements.
+fn list_predicate_parser(input: Span) -> IResult<Predicate> {
+    map(
+        (
+            ws(located(char('['))),
+            separated_list0(ws(char(',')), predicate_parser),
+            ws(located(char(']'))),
+        ),
+        |(open, predicates, close)| {
+            let nil_location = if predicates.is_empty() { open } else { close };
+            let nil = Predicate::Ctor(ctor_id_at("Nil", nil_location), vec![]);
+            predicates.into_iter().rev().fold(nil, |tail, head| {
+                Predicate::Ctor(ctor_id_at("Cons", head.location()), vec![head, tail])
+            })
+        },
+    )
     .parse(input)
 }
 
-fn predicate_parser(input: Span) -> IResult<Predicate> {
-    ws(alt((
-        // Parse negative number predicates.
-        map_res((tag("-"), ws(digit1)), |(_, digits)| {
-            digits
-                .parse()
-                .map(|x: i64| Predicate::Int(-x, (&input).into()))
+/// Parse a number, string or character which must be matched exactly.
+fn literal_predicate_parser(input: Span) -> IResult<Predicate> {
+    alt((
+        // Negative numbers are located at their sign. The magnitude is negated as a big integer,
+        // since that of `i64::MIN` does not fit in an `i64`.
+        map_opt(
+            consumed(preceded(char('-'), ws(integer_literal))),
+            |(span, value)| {
+                Some(integer_predicate(
+                    -value.to_bigint()?,
+                    Location::from(&span),
+                ))
+            },
+        ),
+        map_opt(consumed(integer_literal), |(span, value)| {
+            Some(integer_predicate(value.to_bigint()?, Location::from(&span)))
         }),
-        // Parse positive number predicates.
-        map_res(digit1, |s: Span| {
-            s.parse().map(|x| Predicate::Int(x, (&s).into()))
+        map(consumed(string_literal), |(span, text)| {
+            Predicate::Str(text, Location::from(&span))
         }),
-        tuple_predicate_parser,
-        ctor_predicate_parser,
-        map(id_parser, Predicate::Irrefutable),
+        map(consumed(char_literal), |(span, c)| {
+            Predicate::Char(c, Location::from(&span))
+        }),
+    ))
+    .parse(input)
+}
+
+/// A predicate which matches the integer `value`, whose representation is that of the value.
+fn integer_predicate(value: BigInt, location: Location) -> Predicate {
+    match i64::try_from(&value) {
+        Ok(value) => Predicate::Int(value, location),
+        Err(_) => Predicate::BigInt(value, location),
+    }
+}
+
+/// Parse `name`, or `name@predicate` to bind the whole of a value which matches `predicate`.
+fn binding_predicate_parser(input: Span) -> IResult<Predicate> {
+    map(
+        pair(
+            id_parser,
+            opt(preceded(
+                terminated(char('@'), not(satisfy(is_operator_char))),
+                cut(atomic_predicate_parser),
+            )),
+        ),
+        |(id, predicate)| match predicate {
+            Some(predicate) => Predicate::As(id, Box::new(predicate)),
+            None => Predicate::Irrefutable(id),
+        },
+    )
+    .parse(input)
+}
+
+fn ctor_predicate_parser(input: Span) -> IResult<Predicate> {
+    ws(alt((
+        record_predicate_parser,
+        map(
+            pair(ctor_id_parser, many0(atomic_predicate_parser)),
+            |(ctor, preds)| Predicate::Ctor(ctor, preds),
+        ),
     )))
     .parse(input)
 }
 
-fn match_parser(input: Span) -> IResult<Value> {
+/// Parse `Person { name = predicate }`, which matches the named fields of a constructor and
+/// ignores the rest.
+fn record_predicate_parser(input: Span) -> IResult<Predicate> {
     map(
-        (
-            ws(tag("match")),
-            cut((
-                expr_parser,
-                ws(char(':')),
-                many1((
-                    predicate_parser,
-                    ws(tag("->")),
-                    delimited(ws(char('(')), expr_parser, ws(char(')'))),
+        pair(
+            ctor_id_parser,
+            delimited(
+                ws(char('{')),
+                cut(separated_list0(
+                    ws(char(',')),
+                    separated_pair(id_parser, ws(char('=')), predicate_parser),
                 )),
+                cut(ws(char('}'))),
+            ),
+        ),
+        |(ctor, fields)| Predicate::Record(ctor, fields),
+    )
+    .parse(input)
+}
+
+/// Predicates which can appear as function parameters or constructor arguments without being
+/// parenthesized.
+fn atomic_predicate_parser(input: Span) -> IResult<Predicate> {
+    ws(alt((
+        literal_predicate_parser,
+        tuple_predicate_parser,
+        list_predicate_parser,
+        map(located(keyword("_")), Predicate::Wildcard),
+        map(
+            pair(located(char('~')), cut(atomic_predicate_parser)),
+            |(location, predicate)| Predicate::Lazy(Box::new(predicate), location),
+        ),
+        record_predicate_parser,
+        map(ctor_id_parser, |ctor| Predicate::Ctor(ctor, vec![])),
+        binding_predicate_parser,
+    )))
+    .parse(input)
+}
+
+/// Parse a predicate, which may be a constructor applied to predicates, or `head:tail` to match
+/// a non-empty list.
+fn predicate_parser(input: Span) -> IResult<Predicate> {
+    map(
+        pair(
+            alt((ctor_predicate_parser, atomic_predicate_parser)),
+            opt(pair(
+                ws(located(terminated(
+                    char(':'),
+                    not(satisfy(is_operator_char)),
+                ))),
+                cut(predicate_parser),
             )),
         ),
-        |(_, (subject, _, patterns))| Value::Match {
+        |(head, tail)| match tail {
+            Some((location, tail)) => {
+                Predicate::Ctor(ctor_id_at("Cons", location), vec![head, tail])
+            }
+            None => head,
+        },
+    )
+    .parse(input)
+}
+
+/// Parse `match expr : arms`. The arms are either laid out one per line as `pattern -> expr`, or,
+/// when they start on the line of the `:`, may be written explicitly as
+/// `pattern -> (expr) pattern -> (expr) ...`.
+fn match_parser(input: Span) -> IResult<Value> {
+    let (input, location) = ws(located(keyword("match"))).parse(input)?;
+    let (input, (subject, colon)) =
+        cut(pair(with_cons(false, expr_parser), ws(located(char(':'))))).parse(input)?;
+    let (input, pattern_exprs) = if input.location_line() == colon.line {
+        cut(alt((
+            many1(explicit_match_arm_parser),
+            layout_block(match_arm_parser, Semicolons::Never),
+        )))
+        .parse(input)?
+    } else {
+        cut(layout_block(match_arm_parser, Semicolons::Never)).parse(input)?
+    };
+    Ok((
+        input,
+        Value::Match {
             subject: Box::new(subject),
-            pattern_exprs: patterns
-                .into_iter()
-                .map(|(predicate, _, expr)| PatternExpr { predicate, expr })
-                .collect(),
+            pattern_exprs,
+            location,
         },
+    ))
+}
+
+/// Parse `pattern -> expr`, or `pattern | condition -> expr ...` with guards.
+fn match_arm_parser(input: Span) -> IResult<PatternExpr> {
+    map(
+        pair(
+            predicate_parser,
+            alt((
+                preceded(ws(tag("->")), expr_parser),
+                guards_parser("->", expr_parser),
+            )),
+        ),
+        |(predicate, expr)| PatternExpr { predicate, expr },
     )
     .parse(input)
 }
 
-fn let_parser(input: Span) -> IResult<Value> {
+/// Parse `pattern -> (expr)`, or `pattern | condition -> (expr) ...` with guards.
+fn explicit_match_arm_parser(input: Span) -> IResult<PatternExpr> {
+    fn parenthesized(input: Span) -> IResult<Value> {
+        delimited(ws(char('(')), expr_parser, ws(char(')'))).parse(input)
+    }
+    map(
+        pair(
+            predicate_parser,
+            alt((
+                preceded(ws(tag("->")), parenthesized),
+                guards_parser("->", parenthesized),
+            )),
+        ),
+        |(predicate, expr)| PatternExpr { predicate, expr },
+    )
+    .parse(input)
+}
+
+/// Parse guards such as `| condition = body | condition = body`, where `separator` comes between
+/// each condition and its body. Each condition must evaluate to `True` or `False`.
+fn guards_parser<'a>(
+    separator: &'static str,
+    body: fn(Span<'a>) -> IResult<'a, Value>,
+) -> impl Parser<Span<'a>, Output = Value, Error = SyntaxError<'a>> {
     map(
+        many1(pair(
+            ws(located(char('|'))),
+            cut(separated_pair(expr_parser, ws(tag(separator)), body)),
+        )),
+        |guards| {
+            let location = guards[0].0;
+            Value::Guarded {
+                alternatives: guards.into_iter().map(|(_, guard)| guard).collect(),
+                location,
+            }
+        },
+    )
+}
+
+fn let_parser(input: Span) -> IResult<Value> {
+    map_res(
         (
-            ws(tag("let")),
-            id_parser,
-            ws(char('=')),
-            expr_parser,
+            ws(keyword("let")),
+            with_cons(false, layout_block(binding_parser, Semicolons::Anywhere)),
             ws(char(':')),
             expr_parser,
         ),
-        |(_, name, _, value, _, body)| Value::Let {
-            name,
-            value: Box::new(value),
-            body: Box::new(body),
+        |(_, decls, _, body)| -> Result<Value, PitaError> {
+            Ok(Value::Let {
+                bindings: build_bindings(decls)?,
+                body: Box::new(body),
+            })
         },
     )
     .parse(input)
@@ -168,7 +1066,7 @@ fn do_line_parser(input: Span) -> IResult<DoLine> {
         }),
         // let syntax: let x = expr
         map(
-            (ws(tag("let")), id_parser, ws(char('=')), expr_parser),
+            (ws(keyword("let")), id_parser, ws(char('=')), expr_parser),
             |(_, id, _, expr)| DoLine::Let(id, expr),
         ),
         // expression by itself
@@ -177,36 +1075,81 @@ fn do_line_parser(input: Span) -> IResult<DoLine> {
     .parse(input)
 }
 
+/// Parse `do { x <- expr; let y = expr; expr }`, or the same lines laid out without braces.
 fn do_parser(input: Span) -> IResult<Value> {
     map_res(
-        (
-            ws(tag("do")),
-            separated_list0(ws(char(';')), do_line_parser),
+        preceded(
+            ws(keyword("do")),
+            cut(alt((
+                braced_block(do_line_parser),
+                layout_block(do_line_parser, Semicolons::SameLine),
+            ))),
         ),
-        |(_, lines)| convert_do_notation(&lines),
+        |lines| convert_do_notation(&lines),
     )
     .parse(input)
 }
 
+/// Parse `\x y (a, b) -> expr`, where each parameter may be any predicate.
 fn lambda_parser(input: Span) -> IResult<Value> {
     map(
-        (id_parser, ws(tag("->")), expr_parser),
-        |(param, _, body)| Value::Lambda {
-            param,
-            body: Box::new(body),
-        },
+        (
+            ws(located(char('\\'))),
+            cut((many1(atomic_predicate_parser), ws(tag("->")), expr_parser)),
+        ),
+        |(location, (params, _, body))| build_lambda(params, body, location),
     )
     .parse(input)
 }
 
+/// Parse `()`, `(expr)`, `(expr, expr, ...)`, or one of the operator sections `(op expr)` and
+/// `(expr op)`. The operand of a section is grouped as though it were parenthesized. There is no
+/// section `(- expr)`, which negates `expr` instead.
 fn tuple_ctor_parser(input: Span) -> IResult<Value> {
-    map(
-        delimited(
-            ws(char('(')),
-            separated_list0(ws(char(',')), expr_parser),
-            ws(char(')')),
+    delimited(
+        ws(char('(')),
+        with_cons(
+            true,
+            alt((
+                // (op expr) is \x -> op x expr.
+                map(
+                    pair(
+                        verify(infix_operator_parser, |operator: &Id| {
+                            operator.name() != "-"
+                        }),
+                        expr_parser,
+                    ),
+                    |(operator, operand)| {
+                        let param = gensym(operator.location());
+                        Value::Lambda {
+                            param: param.clone(),
+                            body: Box::new(apply_terms(vec![
+                                Value::Id(operator),
+                                Value::Id(param),
+                                operand,
+                            ])),
+                        }
+                    },
+                ),
+                map_opt(
+                    pair(
+                        separated_list0(ws(char(',')), expr_parser),
+                        opt(infix_operator_parser),
+                    ),
+                    |(mut dims, operator)| match operator {
+                        // (expr op) is op expr.
+                        Some(operator) if dims.len() == 1 => {
+                            Some(apply_terms(vec![Value::Id(operator), dims.remove(0)]))
+                        }
+                        Some(_) => None,
+                        // A single parenthesized expression is just grouping.
+                        None if dims.len() == 1 => Some(dims.remove(0)),
+                        None => Some(Value::Tuple { dims }),
+                    },
+                ),
+            )),
         ),
-        |exprs| Value::Tuple { dims: exprs },
+        ws(char(')')),
     )
     .parse(input)
 }
@@ -214,86 +1157,806 @@ fn tuple_ctor_parser(input: Span) -> IResult<Value> {
 fn if_then_else_parser(input: Span) -> IResult<Value> {
     map(
         (
-            ws(tag("if")),
+            ws(located(keyword("if"))),
             cut((
                 expr_parser,
-                ws(tag("then")),
+                ws(keyword("then")),
                 expr_parser,
-                ws(tag("else")),
+                ws(keyword("else")),
                 expr_parser,
             )),
         ),
-        |(_, (condition, _, then_expr, _, else_expr))| Value::Match {
-            subject: Box::new(condition),
-            pattern_exprs: vec![
-                PatternExpr {
-                    predicate: Predicate::Ctor(internal_ctor_id("True"), vec![]),
-                    expr: then_expr,
-                },
-                PatternExpr {
-                    predicate: Predicate::Ctor(internal_ctor_id("False"), vec![]),
-                    expr: else_expr,
-                },
-            ],
+        |(location, (condition, _, then_expr, _, else_expr))| {
+            if_then_else(condition, then_expr, else_expr, location)
         },
     )
     .parse(input)
 }
 
-fn callsite_parser(input: Span) -> IResult<Value> {
-    map(many1(callsite_term_parser), |mut terms| {
-        if terms.len() == 1 {
-            terms.remove(0)
-        } else {
-            terms
-                .into_iter()
-                .fold(None, |acc: Option<Value>, term| match acc {
-                    None => Some(term),
-                    Some(callsite) => Some(Value::Callsite {
-                        function: Box::new(callsite),
-                        argument: Box::new(term),
-                    }),
-                })
-                .unwrap()
+/// Build `if condition then then_expr else else_expr` as a match on `True` and `False`.
+fn if_then_else(condition: Value, then_expr: Value, else_expr: Value, location: Location) -> Value {
+    Value::Match {
+        subject: Box::new(condition),
+        pattern_exprs: vec![
+            PatternExpr {
+                predicate: Predicate::Ctor(internal_ctor_id("True"), vec![]),
+                expr: then_expr,
+            },
+            PatternExpr {
+                predicate: Predicate::Ctor(internal_ctor_id("False"), vec![]),
+                expr: else_expr,
+            },
+        ],
+        location,
+    }
+}
+
+/// Parse a list: `[]`, `[a, b, c]`, a range such as `[1..]`, `[1..10]`, `[1, 3..]` or
+/// `[1, 3..9]`, or a comprehension such as `[x * y | x <- xs, y <- ys, x < y]`. Ranges and
+/// comprehensions are desugared into calls to functions of the prelude.
+fn list_parser(input: Span) -> IResult<Value> {
+    let (input, location) = ws(located(char('['))).parse(input)?;
+    with_cons(
+        true,
+        cut(terminated(
+            alt((
+                map(peek(ws(char(']'))), move |_| {
+                    build_list(Vec::new(), location)
+                }),
+                map_res(pair(expr_parser, list_tail_parser), move |(first, tail)| {
+                    convert_list(first, tail, location)
+                }),
+            )),
+            ws(char(']')),
+        )),
+    )
+    .parse(input)
+}
+
+/// Build the list of `items` from `Cons` and `Nil`.
+fn build_list(items: Vec<Value>, location: Location) -> Value {
+    let nil = Value::Id(ctor_id_at("Nil", location));
+    items.into_iter().rev().fold(nil, |tail, head| {
+        let cons = Value::Id(ctor_id_at("Cons", head.location()));
+        apply_terms(vec![cons, head, tail])
+    })
+}
+
+/// What follows the first item of a list.
+enum ListTail {
+    /// `, b, c`, which may end in `..` and an optional limit.
+    Items(Vec<Value>, Option<Option<Value>>),
+    /// `| qualifiers` of a comprehension.
+    Comprehension(Vec<Qualifier>),
+}
+
+fn list_tail_parser(input: Span) -> IResult<ListTail> {
+    alt((
+        map(
+            preceded(
+                ws(char('|')),
+                cut(separated_list1(ws(char(',')), qualifier_parser)),
+            ),
+            ListTail::Comprehension,
+        ),
+        map(
+            pair(
+                many0(preceded(ws(char(',')), expr_parser)),
+                opt(preceded(ws(tag("..")), opt(expr_parser))),
+            ),
+            |(items, range)| ListTail::Items(items, range),
+        ),
+    ))
+    .parse(input)
+}
+
+/// A qualifier of a list comprehension.
+enum Qualifier {
+    /// `pattern <- list`, which draws each element of `list` that matches `pattern`.
+    Generator(Predicate, Value),
+    /// `let name = expr`.
+    Let(Decl),
+    /// A condition which each combination of elements must satisfy.
+    Guard(Value),
+}
+
+fn qualifier_parser(input: Span) -> IResult<Qualifier> {
+    alt((
+        map(
+            preceded(ws(keyword("let")), cut(binding_parser)),
+            Qualifier::Let,
+        ),
+        map(
+            separated_pair(predicate_parser, ws(tag("<-")), cut(expr_parser)),
+            |(predicate, list)| Qualifier::Generator(predicate, list),
+        ),
+        map(expr_parser, Qualifier::Guard),
+    ))
+    .parse(input)
+}
+
+/// The functions of the prelude which lists, negation and `do` blocks desugar to, by way of their
+/// `prelude_alias`.
+pub(crate) const PRELUDE_ALIASES: [&str; 8] = [
+    "concatMap",
+    "negate",
+    "enumFrom",
+    "enumFromTo",
+    "enumFromThen",
+    "enumFromThenTo",
+    ">>=",
+    ">>",
+];
+
+/// Desugar a list which starts with `first`.
+fn convert_list(first: Value, tail: ListTail, location: Location) -> Result<Value, PitaError> {
+    let call = |name: &str, args: Vec<Value>| {
+        let mut terms = vec![Value::Id(prelude_alias(name, location))];
+        terms.extend(args);
+        apply_terms(terms)
+    };
+    Ok(match tail {
+        ListTail::Items(mut items, None) => {
+            items.insert(0, first);
+            build_list(items, location)
         }
+        ListTail::Items(items, Some(limit)) => match (&items[..], limit) {
+            ([], None) => call("enumFrom", vec![first]),
+            ([], Some(to)) => call("enumFromTo", vec![first, to]),
+            ([then], None) => call("enumFromThen", vec![first, then.clone()]),
+            ([then], Some(to)) => call("enumFromThenTo", vec![first, then.clone(), to]),
+            _ => {
+                return Err(error!(
+                    "{location}: a range may only give its first two elements"
+                ))
+            }
+        },
+        ListTail::Comprehension(qualifiers) => convert_comprehension(first, qualifiers, location)?,
     })
+}
+
+/// Desugar `[element | qualifiers]` as in the Haskell report: each generator maps over its list
+/// with `concatMap`, and each guard keeps or drops the elements which follow from it.
+fn convert_comprehension(
+    element: Value,
+    qualifiers: Vec<Qualifier>,
+    location: Location,
+) -> Result<Value, PitaError> {
+    let nil = || Value::Id(ctor_id_at("Nil", location));
+    let mut result = build_list(vec![element], location);
+    for qualifier in qualifiers.into_iter().rev() {
+        result = match qualifier {
+            Qualifier::Guard(condition) => {
+                let location = condition.location();
+                if_then_else(condition, result, nil(), location)
+            }
+            Qualifier::Let(decl) => Value::Let {
+                bindings: build_bindings(vec![decl])?,
+                body: Box::new(result),
+            },
+            Qualifier::Generator(predicate, list) => {
+                let location = predicate.location();
+                let function = if predicate.is_irrefutable() {
+                    build_lambda(vec![predicate], result, location)
+                } else {
+                    // Elements which do not match are skipped.
+                    let param = gensym(location);
+                    Value::Lambda {
+                        param: param.clone(),
+                        body: Box::new(Value::Match {
+                            subject: Box::new(Value::Id(param)),
+                            pattern_exprs: vec![
+                                PatternExpr {
+                                    predicate,
+                                    expr: result,
+                                },
+                                PatternExpr {
+                                    predicate: Predicate::Wildcard(location),
+                                    expr: nil(),
+                                },
+                            ],
+                            location,
+                        }),
+                    }
+                };
+                let concat_map = prelude_alias("concatMap", location);
+                apply_terms(vec![Value::Id(concat_map), function, list])
+            }
+        };
+    }
+    Ok(result)
+}
+
+/// Parse `f a b c`. A block expression may be the last argument without being parenthesized,
+/// since it extends as far to the right as possible anyway.
+fn callsite_parser(input: Span) -> IResult<Value> {
+    alt((
+        map(
+            pair(many1(atomic_expr_parser), opt(block_expr_parser)),
+            |(mut terms, block)| {
+                terms.extend(block);
+                apply_terms(terms)
+            },
+        ),
+        block_expr_parser,
+    ))
+    .parse(input)
+}
+
+/// Parse `a + b * c`. Operators are kept as a flat chain, since their fixity declarations may
+/// appear anywhere in the program; the chain is grouped into callsites once they are all known.
+/// Any operand may be negated, as in `-a` or `a - -b`.
+fn infix_parser(input: Span) -> IResult<Value> {
+    map(
+        pair(
+            operand_parser,
+            many0(pair(infix_operator_parser, operand_parser)),
+        ),
+        |((negation, first), rest)| {
+            if rest.is_empty() && negation.is_none() {
+                return first;
+            }
+            let mut negations = vec![negation];
+            let mut operands = vec![first];
+            let mut operators = Vec::new();
+            for (operator, (negation, operand)) in rest {
+                operators.push(operator);
+                negations.push(negation);
+                operands.push(operand);
+            }
+            Value::Infix {
+                operands,
+                operators,
+                negations,
+            }
+        },
+    )
     .parse(input)
 }
 
-fn callsite_term_parser(input: Span) -> IResult<Value> {
+/// Parse an operand of an infix chain, along with the `-` which negates it, if any.
+fn operand_parser(input: Span) -> IResult<(Option<Id>, Value)> {
+    pair(
+        opt(ws(verify(operator_symbol, |operator: &Id| {
+            operator.name() == "-"
+        }))),
+        callsite_parser,
+    )
+    .parse(input)
+}
+
+/// Fold `f a b c` into the curried callsite `(((f a) b) c)`.
+fn apply_terms(terms: Vec<Value>) -> Value {
+    terms
+        .into_iter()
+        .reduce(|callsite, term| Value::Callsite {
+            function: Box::new(callsite),
+            argument: Box::new(term),
+        })
+        .expect("callsites have at least one term")
+}
+
+/// Expressions which can be applied, or be arguments, without being parenthesized. Other than
+/// literals, they may be followed by field accesses such as `.name` and record updates such as
+/// `{ name = expr }`, which bind more tightly than application.
+fn atomic_expr_parser(input: Span) -> IResult<Value> {
     ws(alt((
         string_literal_parser,
-        tuple_ctor_parser,
+        char_literal_parser,
+        number_parser,
+        map(
+            pair(
+                alt((
+                    map(operator_id_parser, Value::Id),
+                    tuple_ctor_parser,
+                    list_parser,
+                    map(id_parser, Value::Id),
+                )),
+                many0(record_suffix_parser),
+            ),
+            |(record, suffixes)| {
+                suffixes
+                    .into_iter()
+                    .fold(record, |record, suffix| match suffix {
+                        RecordSuffix::Field(field) => Value::FieldAccess {
+                            record: Box::new(record),
+                            field,
+                        },
+                        RecordSuffix::Update(fields, location) => Value::RecordUpdate {
+                            record: Box::new(record),
+                            fields,
+                            location,
+                        },
+                    })
+            },
+        ),
+    )))
+    .parse(input)
+}
+
+enum RecordSuffix {
+    /// `.name`, which selects the field `name`.
+    Field(Id),
+    Update(Vec<(Id, Value)>, Location),
+}
+
+fn record_suffix_parser(input: Span) -> IResult<RecordSuffix> {
+    alt((
+        map(
+            preceded(
+                char('.'),
+                map_res(map(identifier, Token::from), parse_id::<IdImpl>),
+            ),
+            RecordSuffix::Field,
+        ),
+        map(
+            pair(
+                ws(located(char('{'))),
+                within(
+                    Block::default(),
+                    with_cons(
+                        true,
+                        cut(terminated(
+                            separated_list1(
+                                ws(char(',')),
+                                separated_pair(
+                                    id_parser,
+                                    ws(terminated(char('='), not(satisfy(is_operator_char)))),
+                                    expr_parser,
+                                ),
+                            ),
+                            ws(char('}')),
+                        )),
+                    ),
+                ),
+            ),
+            |(location, fields)| RecordSuffix::Update(fields, location),
+        ),
+    ))
+    .parse(input)
+}
+
+/// Expressions which begin with a keyword or `\`, and extend as far to the right as possible.
+fn block_expr_parser(input: Span) -> IResult<Value> {
+    ws(alt((
+        lambda_parser,
         let_parser,
         do_parser,
         if_then_else_parser,
         match_parser,
-        number_parser,
-        map(id_parser, Value::Id),
     )))
     .parse(input)
 }
 
+/// Parse `name patterns = expr`, as found in top-level declarations and `let` bindings. An
+/// operator may also be defined infix, as in `x <+> y = expr`. Instead of `= expr`, the body may
+/// be guards, as in `| condition = expr`. The body may be followed by a `where` block, whose
+/// bindings scope over the body and can see the names bound by `patterns`.
+fn binding_parser(input: Span) -> IResult<Decl> {
+    map_res(
+        (
+            alt((
+                map(
+                    (
+                        atomic_predicate_parser,
+                        // `f ~p` is a lazy predicate rather than a definition of `~`, and `:`
+                        // is a constructor.
+                        verify(infix_operator_parser, |name: &Id| {
+                            name.name() != "~" && name.name() != "Cons"
+                        }),
+                        atomic_predicate_parser,
+                    ),
+                    |(lhs, name, rhs)| (name, vec![lhs, rhs]),
+                ),
+                pair(binder_parser, many0(atomic_predicate_parser)),
+            )),
+            alt((
+                preceded(ws(char('=')), expr_parser),
+                guards_parser("=", expr_parser),
+            )),
+            opt(where_parser),
+        ),
+        |((name, patterns), body, where_decls)| -> Result<Decl, PitaError> {
+            let body = match where_decls {
+                Some(decls) => Value::Let {
+                    bindings: build_bindings(decls)?,
+                    body: Box::new(body),
+                },
+                None => body,
+            };
+            Ok(Decl {
+                name,
+                patterns,
+                body,
+                doc: None,
+            })
+        },
+    )
+    .parse(input)
+}
+
+/// Parse `where` followed by bindings, either laid out or in braces.
+fn where_parser(input: Span) -> IResult<Vec<Decl>> {
+    preceded(
+        ws(keyword("where")),
+        cut(alt((
+            braced_block(binding_parser),
+            layout_block(binding_parser, Semicolons::SameLine),
+        ))),
+    )
+    .parse(input)
+}
+
 fn decl_parser(input: Span) -> IResult<Decl> {
+    terminated(binding_parser, ws(char(';'))).parse(input)
+}
+
+fn atomic_type_parser(input: Span) -> IResult<TypeExpr> {
+    ws(alt((
+        map(ctor_id_parser, |name| TypeExpr::Con(name, vec![])),
+        map(id_parser, TypeExpr::Var),
+        map(
+            delimited(
+                ws(char('(')),
+                separated_list0(ws(char(',')), type_parser),
+                ws(char(')')),
+            ),
+            |mut types| {
+                // A single parenthesized type is just grouping.
+                if types.len() == 1 {
+                    types.remove(0)
+                } else {
+                    TypeExpr::Tuple(types)
+                }
+            },
+        ),
+    )))
+    .parse(input)
+}
+
+fn type_application_parser(input: Span) -> IResult<TypeExpr> {
+    alt((
+        map(
+            pair(ctor_id_parser, many1(atomic_type_parser)),
+            |(name, args)| TypeExpr::Con(name, args),
+        ),
+        map(pair(id_parser, many1(atomic_type_parser)), |(var, args)| {
+            TypeExpr::App(var, args)
+        }),
+        atomic_type_parser,
+    ))
+    .parse(input)
+}
+
+/// Parse a type, where `->` associates to the right.
+fn type_parser(input: Span) -> IResult<TypeExpr> {
+    map(
+        pair(
+            type_application_parser,
+            opt(preceded(ws(tag("->")), type_parser)),
+        ),
+        |(domain, range)| match range {
+            Some(range) => TypeExpr::Function(Box::new(domain), Box::new(range)),
+            None => domain,
+        },
+    )
+    .parse(input)
+}
+
+/// Parse the signature of a builtin, such as `Int -> Int -> Int`.
+pub(crate) fn builtin_signature(signature: &str) -> Result<TypeExpr, PitaError> {
+    let span = new_span(signature, "<builtin>");
+    let (_, type_expr) = all_consuming(type_parser)
+        .parse(span)
+        .map_err(parse_error)?;
+    Ok(type_expr)
+}
+
+/// Parse a constructor such as `Cons a (List a)`, or a record constructor with named fields such
+/// as `Person { name :: String, age :: Int }`.
+fn ctor_decl_parser(input: Span) -> IResult<CtorDecl> {
+    alt((
+        map(
+            pair(
+                ctor_id_parser,
+                delimited(
+                    ws(char('{')),
+                    cut(separated_list0(
+                        ws(char(',')),
+                        separated_pair(id_parser, ws(tag("::")), type_parser),
+                    )),
+                    cut(ws(char('}'))),
+                ),
+            ),
+            |(name, fields)| {
+                let (field_names, fields) = fields.into_iter().unzip();
+                CtorDecl {
+                    name,
+                    fields,
+                    field_names,
+                }
+            },
+        ),
+        map(
+            pair(ctor_id_parser, many0(atomic_type_parser)),
+            |(name, fields)| CtorDecl {
+                name,
+                fields,
+                field_names: Vec::new(),
+            },
+        ),
+    ))
+    .parse(input)
+}
+
+fn data_parser(input: Span) -> IResult<DataDecl> {
     map(
         (
-            id_parser,
-            many0(predicate_parser),
-            ws(char('=')),
-            expr_parser,
-            ws(char(';')),
+            ws(keyword("data")),
+            cut((
+                ctor_id_parser,
+                many0(id_parser),
+                ws(char('=')),
+                separated_list1(ws(char('|')), ctor_decl_parser),
+                ws(char(';')),
+            )),
+        ),
+        |(_, (name, params, _, ctors, _))| DataDecl {
+            name,
+            params,
+            ctors,
+            doc: None,
+        },
+    )
+    .parse(input)
+}
+
+/// Parse a constraint such as `Eq a`.
+fn constraint_parser(input: Span) -> IResult<Constraint> {
+    map(pair(ctor_id_parser, id_parser), |(class, var)| Constraint {
+        class,
+        var,
+    })
+    .parse(input)
+}
+
+/// Parse the constraints which precede `=>`, as in `Eq a =>` or `(Eq a, Show b) =>`.
+fn context_parser(input: Span) -> IResult<Vec<Constraint>> {
+    terminated(
+        alt((
+            map(constraint_parser, |constraint| vec![constraint]),
+            delimited(
+                ws(char('(')),
+                separated_list1(ws(char(',')), constraint_parser),
+                ws(char(')')),
+            ),
+        )),
+        ws(tag("=>")),
+    )
+    .parse(input)
+}
+
+/// Parse `name :: type`, where the type may have a context, as in `name :: Eq a => type`.
+fn signature_body_parser(input: Span) -> IResult<Signature> {
+    map(
+        (
+            binder_parser,
+            ws(tag("::")),
+            cut((
+                map(opt(context_parser), Option::unwrap_or_default),
+                type_parser,
+            )),
         ),
-        |(name, patterns, _, body, _)| Decl {
+        |(name, _, (context, type_expr))| Signature {
             name,
-            patterns,
-            body,
+            context,
+            type_expr,
+            doc: None,
+        },
+    )
+    .parse(input)
+}
+
+/// Parse `name :: type;`.
+fn signature_parser(input: Span) -> IResult<Signature> {
+    terminated(signature_body_parser, cut(ws(char(';')))).parse(input)
+}
+
+/// An item of the `where` block of a class.
+enum ClassItem {
+    Method(Signature),
+    Default(Decl),
+}
+
+fn class_item_parser(input: Span) -> IResult<ClassItem> {
+    alt((
+        map(signature_body_parser, ClassItem::Method),
+        map(binding_parser, ClassItem::Default),
+    ))
+    .parse(input)
+}
+
+/// Parse `class Eq a => Ord a where ...;`, whose block declares the methods of the class along
+/// with any default definitions of them.
+fn class_parser(input: Span) -> IResult<ClassDecl> {
+    map(
+        (
+            ws(keyword("class")),
+            cut((
+                map(opt(context_parser), Option::unwrap_or_default),
+                ctor_id_parser,
+                id_parser,
+                ws(keyword("where")),
+                alt((
+                    braced_block(class_item_parser),
+                    layout_block(class_item_parser, Semicolons::SameLine),
+                )),
+                ws(char(';')),
+            )),
+        ),
+        |(_, (context, name, var, _, items, _))| {
+            let mut methods = Vec::new();
+            let mut defaults = Vec::new();
+            for item in items {
+                match item {
+                    ClassItem::Method(signature) => methods.push(signature),
+                    ClassItem::Default(decl) => defaults.push(decl),
+                }
+            }
+            ClassDecl {
+                name,
+                var,
+                superclasses: context,
+                methods,
+                defaults,
+                doc: None,
+            }
+        },
+    )
+    .parse(input)
+}
+
+/// Parse the type of an instance, which is a type constructor applied to type variables, such as
+/// `Int`, `(List a)` or `(a, b)`.
+fn instance_type_parser(input: Span) -> IResult<(String, Vec<Id>)> {
+    alt((
+        map(ctor_id_parser, |name| (name.name().to_string(), Vec::new())),
+        delimited(
+            ws(char('(')),
+            alt((
+                map(pair(ctor_id_parser, many0(id_parser)), |(name, params)| {
+                    (name.name().to_string(), params)
+                }),
+                map(
+                    verify(
+                        separated_list0(ws(char(',')), id_parser),
+                        |params: &Vec<Id>| params.len() != 1,
+                    ),
+                    |params| (tuple_name(params.len()), params),
+                ),
+            )),
+            ws(char(')')),
+        ),
+    ))
+    .parse(input)
+}
+
+/// Parse `instance Eq a => Eq (List a) where ...;`, whose block defines the methods of the class.
+fn instance_parser(input: Span) -> IResult<InstanceDecl> {
+    map(
+        (
+            ws(keyword("instance")),
+            cut((
+                map(opt(context_parser), Option::unwrap_or_default),
+                ctor_id_parser,
+                instance_type_parser,
+                map(opt(where_parser), Option::unwrap_or_default),
+                ws(char(';')),
+            )),
+        ),
+        |(_, (context, class, (type_name, params), decls, _))| InstanceDecl {
+            class,
+            context,
+            type_name,
+            params,
+            decls,
+            doc: None,
+        },
+    )
+    .parse(input)
+}
+
+/// Parse `infixl 6 +, -;`.
+fn fixity_parser(input: Span) -> IResult<FixityDecl> {
+    map(
+        (
+            ws(alt((
+                value(Assoc::Left, keyword("infixl")),
+                value(Assoc::Right, keyword("infixr")),
+                value(Assoc::None, keyword("infix")),
+            ))),
+            cut((
+                ws(map_opt(
+                    terminated(satisfy(|c| c.is_ascii_digit()), not(digit1)),
+                    |c| c.to_digit(10),
+                )),
+                separated_list1(ws(char(',')), infix_operator_parser),
+                ws(char(';')),
+            )),
+        ),
+        |(assoc, (precedence, operators, _))| FixityDecl {
+            assoc,
+            precedence: precedence as u8,
+            operators,
+            doc: None,
         },
     )
     .parse(input)
 }
 
-pub(crate) fn program_parser(input: Span) -> IResult<Vec<Decl>> {
-    terminated(many0(decl_parser), multispace0).parse(input)
+/// Parse a top-level item, along with the doc comments directly before it.
+fn item_parser(input: Span) -> IResult<Item> {
+    let (input, doc) = doc_comment_parser(input)?;
+    let (start, _) = blank(input)?;
+    let outer = start.extra.item_column;
+    let item_column = start.naive_get_utf8_column();
+    let (rest, item) = alt((
+        map(data_parser, Item::Data),
+        map(class_parser, Item::Class),
+        map(instance_parser, Item::Instance),
+        map(fixity_parser, Item::Fixity),
+        map(signature_parser, Item::Signature),
+        map(decl_parser, Item::Decl),
+    ))
+    .parse(start.map_extra(|extra| SpanExtra {
+        item_column,
+        ..extra
+    }))?;
+    let rest = rest.map_extra(|extra| SpanExtra {
+        item_column: outer,
+        ..extra
+    });
+    Ok((rest, item.with_doc(doc)))
+}
+
+/// Parse every item of a program. Input which does not parse as an item is an error.
+pub(crate) fn program_parser(mut input: Span) -> IResult<Vec<Item>> {
+    let mut items = Vec::new();
+    loop {
+        let (rest, _) = blank(input)?;
+        if rest.is_empty() {
+            return Ok((rest, items));
+        }
+        let (rest, item) = item_parser(input)?;
+        items.push(item);
+        input = rest;
+    }
+}
+
+/// Describe a failure to parse by where it happened, and by what was expected there when that is
+/// a punctuation mark or two. Many characters may start a term, so listing them would not help.
+pub(crate) fn parse_error(error: nom::Err<SyntaxError>) -> PitaError {
+    match error {
+        nom::Err::Incomplete(_) => error!("incomplete input"),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            let location = Location::from(&e.input);
+            match (e.kind, &e.expected[..], e.input.chars().next()) {
+                (ErrorKind::Escaped, _, _) => {
+                    error!("{location}: pita parse error: invalid escape sequence")
+                }
+                (ErrorKind::Digit, _, _) => {
+                    error!("{location}: pita parse error: invalid number")
+                }
+                (ErrorKind::Float, _, _) => {
+                    error!("{location}: pita parse error: float is too large")
+                }
+                (_, [c], _) => error!("{location}: pita parse error: expected `{c}`"),
+                (_, [a, b], _) => error!("{location}: pita parse error: expected `{a}` or `{b}`"),
+                (_, _, Some(c)) => error!("{location}: pita parse error: unexpected `{c}`"),
+                (_, _, None) => error!("{location}: pita parse error: unexpected end of input"),
+            }
+        }
+    }
 }
 
 // Helper function to convert do notation into nested expressions
@@ -302,13 +1965,12 @@ fn convert_do_notation(lines: &[DoLine]) -> Result<Value, PitaError> {
         [] => return Err("Empty do block".into()),
         [DoLine::Expr(expr)] => expr.clone(),
         [DoLine::Let(name, value), rest @ ..] => Value::Let {
-            name: name.clone(),
-            value: Box::new(value.clone()),
+            bindings: vec![(name.clone(), value.clone())],
             body: Box::new(convert_do_notation(rest)?),
         },
         [DoLine::Bind(name, expr), rest @ ..] => Value::Callsite {
             function: Box::new(Value::Callsite {
-                function: Box::new(Value::Id(internal_id(">>="))),
+                function: Box::new(Value::Id(prelude_alias(">>=", name.location()))),
                 argument: Box::new(expr.clone()),
             }),
             argument: Box::new(Value::Lambda {
@@ -316,7 +1978,13 @@ fn convert_do_notation(lines: &[DoLine]) -> Result<Value, PitaError> {
                 body: Box::new(convert_do_notation(rest)?),
             }),
         },
-        [DoLine::Expr(_), ..] => return Err("Expression in middle of do block".into()),
+        [DoLine::Expr(expr), rest @ ..] => Value::Callsite {
+            function: Box::new(Value::Callsite {
+                function: Box::new(Value::Id(prelude_alias(">>", expr.location()))),
+                argument: Box::new(expr.clone()),
+            }),
+            argument: Box::new(convert_do_notation(rest)?),
+        },
     })
 }
 
@@ -328,11 +1996,452 @@ enum DoLine {
 }
 
 fn expr_parser(input: Span) -> IResult<Value> {
-    ws(alt((
-        match_parser,
-        number_parser,
-        map(id_parser, Value::Id),
-        // ... other expression types
-    )))
-    .parse(input)
+    infix_parser(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decl_parser, new_span, program_parser};
+    use crate::value::{Decl, Item, Predicate, TypeExpr, Value};
+
+    fn parse_decl(source: &'static str) -> Decl {
+        let (remaining, decl) = decl_parser(new_span(source, "<test>")).unwrap();
+        assert!(remaining.is_empty(), "remaining input: {remaining:?}");
+        decl
+    }
+
+    fn parse_body(source: &'static str) -> String {
+        format!("{:?}", parse_decl(source).body)
+    }
+
+    fn parse_program(source: &'static str) -> Vec<Item> {
+        let (remaining, items) = program_parser(new_span(source, "<test>")).unwrap();
+        assert!(remaining.is_empty(), "remaining input: {remaining:?}");
+        items
+    }
+
+    fn item_names(source: &'static str) -> Vec<String> {
+        parse_program(source).iter().flat_map(Item::names).collect()
+    }
+
+    #[test]
+    fn test_application() {
+        assert_eq!(parse_body("f = g x (h y) 1;"), "(((g x) (h y)) 1)");
+        assert_eq!(parse_body("f = (+) 1 2;"), "((+ 1) 2)");
+    }
+
+    #[test]
+    fn test_infix() {
+        assert_eq!(parse_body("f = g x + 1 * h y;"), "{(g x) + 1 * (h y)}");
+        assert_eq!(parse_body("f = x `div` 2;"), "{x div 2}");
+        assert_eq!(parse_body("f = (a + b) * c;"), "{{a + b} * c}");
+        assert_eq!(parse_body("f = -x;"), "{-x}");
+        assert_eq!(parse_body("f = 3 - -g x;"), "{3 - -(g x)}");
+    }
+
+    #[test]
+    fn test_sections() {
+        assert_eq!(parse_body("f = (1 +);"), "(+ 1)");
+        assert_eq!(parse_body("f = (- 1);"), "{-1}");
+        assert_eq!(parse_body("f = (1 -);"), "(- 1)");
+        let body = parse_decl("f = (`div` g x);").body;
+        let Value::Lambda { param, body } = body else {
+            panic!("expected a lambda, got {body:?}");
+        };
+        assert_eq!(format!("{body:?}"), format!("((div {param}) (g x))"));
+    }
+
+    #[test]
+    fn test_infix_definitions() {
+        let decl = parse_decl("x <+> (a, b) = x;");
+        assert_eq!(decl.name.name(), "<+>");
+        assert_eq!(decl.patterns.len(), 2);
+        assert_eq!(parse_decl("(<+>) x y = x;").name.name(), "<+>");
+    }
+
+    #[test]
+    fn test_lambda() {
+        assert_eq!(parse_body("f = \\x -> g x;"), "λx.(g x)");
+        assert_eq!(parse_body("f = map \\x -> x;"), "(map λx.x)");
+        assert_eq!(parse_body("f = map (\\x -> x) xs;"), "((map λx.x) xs)");
+        assert_eq!(parse_body("f = \\x y -> x;"), "λx.λy.x");
+        let body = parse_decl("f = \\x (a, b) -> a;").body;
+        let Value::Lambda { body, .. } = body else {
+            panic!("expected a lambda, got {body:?}");
+        };
+        let Value::Lambda { body, .. } = *body else {
+            panic!("expected a lambda, got {body:?}");
+        };
+        assert!(
+            matches!(*body, Value::Match { ref pattern_exprs, .. } if pattern_exprs.len() == 1)
+        );
+    }
+
+    #[test]
+    fn test_tuple() {
+        assert_eq!(parse_body("f = (1, g x, \"s\");"), "(1, (g x), \"s\")");
+        assert_eq!(parse_body("f = ();"), "()");
+        assert_eq!(parse_body("f = (g x);"), "(g x)");
+    }
+
+    #[test]
+    fn test_let() {
+        assert_eq!(
+            parse_body("f = let x = 1; y = g x : (x, y);"),
+            "let x = 1; y = (g x) : (x, y)"
+        );
+    }
+
+    #[test]
+    fn test_do() {
+        assert_eq!(
+            parse_body("f = do { x <- g; let y = h x; k y };"),
+            "((prelude$>>= g) λx.let y = (h x) : (k y))"
+        );
+        assert_eq!(parse_body("f = do { g; k };"), "((prelude$>> g) k)");
+        // A `;` which is followed by a new top-level item ends a block without braces.
+        let items = parse_program("f x = do a <- g x; k a; main () = f 1;");
+        let [Item::Decl(f), Item::Decl(main)] = &items[..] else {
+            panic!("expected two declarations, got {items:?}");
+        };
+        assert_eq!(format!("{:?}", f.body), "((prelude$>>= (g x)) λa.(k a))");
+        assert_eq!(main.name.name(), "main");
+    }
+
+    #[test]
+    fn test_classes() {
+        let (remaining, items) = program_parser(new_span(
+            "class Eq a => Ord a where\n  (<=) :: a -> a -> Bool\n  x < y = x <= y;\n\
+             instance (Ord a, Ord b) => Ord (a, b) where (a, b) <= (c, d) = a <= c;\n\
+             fmap :: Functor f => (a -> b) -> f a -> f b;",
+            "<test>",
+        ))
+        .unwrap();
+        assert!(remaining.is_empty(), "remaining input: {remaining:?}");
+        let [Item::Class(class_decl), Item::Instance(instance), Item::Signature(signature)] =
+            &items[..]
+        else {
+            panic!("expected a class, an instance and a signature, got {items:?}");
+        };
+        assert_eq!(class_decl.name.name(), "Ord");
+        assert_eq!(class_decl.superclasses[0].class.name(), "Eq");
+        assert_eq!(class_decl.methods[0].name.name(), "<=");
+        assert_eq!(class_decl.defaults[0].name.name(), "<");
+        assert_eq!(instance.class.name(), "Ord");
+        assert_eq!(instance.type_name, "(,)");
+        assert_eq!(instance.params.len(), 2);
+        assert_eq!(instance.context.len(), 2);
+        assert_eq!(instance.decls[0].name.name(), "<=");
+        assert_eq!(signature.context[0].class.name(), "Functor");
+        assert_eq!(
+            item_names("class C a where c :: a -> Int; instance C Int where c x = x; main () = 1;"),
+            ["C", "C Int", "main"]
+        );
+        assert!(matches!(
+            &signature.type_expr,
+            TypeExpr::Function(_, range) if matches!(&**range, TypeExpr::Function(domain, _)
+                if matches!(&**domain, TypeExpr::App(var, args) if var.name() == "f" && args.len() == 1))
+        ));
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(
+            parse_body("f = do\n  x <- g\n  let y = h x\n  k y;"),
+            parse_body("f = do { x <- g; let y = h x; k y };")
+        );
+        assert_eq!(
+            parse_body("f = let\n  x = 1\n  y = g\n    x\n  : (x, y);"),
+            "let x = 1; y = (g x) : (x, y)"
+        );
+        let body = parse_decl("f x = match x :\n  1 -> g\n  n -> h\n    n;").body;
+        let Value::Match { pattern_exprs, .. } = body else {
+            panic!("expected a match, got {body:?}");
+        };
+        let arms: Vec<String> = pattern_exprs
+            .iter()
+            .map(|pattern_expr| format!("{:?}", pattern_expr.expr))
+            .collect();
+        assert_eq!(arms, ["g", "(h n)"]);
+    }
+
+    #[test]
+    fn test_explicit_match_arms() {
+        let body = parse_decl("f x = match x : 1 -> (g) n -> (h n);").body;
+        assert!(matches!(body, Value::Match { ref pattern_exprs, .. } if pattern_exprs.len() == 2));
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(parse_body("f = g -- the argument follows\n  x;"), "(g x)");
+        assert_eq!(
+            parse_body("f = {- a {- nested -} comment -} g {--} x;"),
+            "(g x)"
+        );
+        assert_eq!(parse_body("f = a --> b ---- comment\n;"), "{a --> b}");
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let source = "--| A signature.\nf :: Int;\n\
+                      --| One.\n--|   Two.\nf = 1;\n\
+                      -- Plain.\ng = 2;\n\
+                      {- Unrelated. -}\n--| Three.\nh = 3; --| Trailing.\n\
+                      i = 4;\n\
+                      --| Separated by a blank line.\n\ninfixl 6 +++;\n\
+                      --| Separated by a comment.\n-- Plain.\ndata D = D;\n\
+                      --| A class.\nclass C a where { c :: a -> Int };\n\
+                      --| An instance.\ninstance C D where { c x = 0 };\n";
+        let (remaining, items) = program_parser(new_span(source, "<test>")).unwrap();
+        assert!(remaining.is_empty(), "remaining input: {remaining:?}");
+        let docs: Vec<(Vec<String>, Option<&str>)> = items
+            .iter()
+            .map(|item| (item.names(), item.doc()))
+            .collect();
+        assert_eq!(
+            docs,
+            [
+                (vec!["f".to_string()], Some("A signature.")),
+                (vec!["f".to_string()], Some("One.\n  Two.")),
+                (vec!["g".to_string()], None),
+                (vec!["h".to_string()], Some("Three.")),
+                (vec!["i".to_string()], None),
+                (vec!["+++".to_string()], None),
+                (vec!["D".to_string()], None),
+                (vec!["C".to_string()], Some("A class.")),
+                (vec!["C D".to_string()], Some("An instance.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_where() {
+        assert_eq!(parse_body("f x = g y where y = x;"), "let y = x : (g y)");
+        assert_eq!(
+            parse_body("f x = g y\n  where\n    y = h\n      x\n    g = k;"),
+            "let y = (h x); g = k : (g y)"
+        );
+        let body = parse_decl("f = g 1 where { g 0 = 1; g n = n };").body;
+        let Value::Let { bindings, body } = body else {
+            panic!("expected a let, got {body:?}");
+        };
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(format!("{body:?}"), "(g 1)");
+        assert_eq!(
+            item_names("f x = y where y = x; main () = f 1;"),
+            ["f", "main"]
+        );
+        // Bindings may follow a `;` on a line which is indented past the start of the item.
+        assert_eq!(
+            item_names("f x = y\n  where y = z; z = x;\nmain () = f 1;"),
+            ["f", "main"]
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        assert_eq!(parse_body("f = p.name;"), "p.name");
+        assert_eq!(parse_body("f = g p.a.b;"), "(g p.a.b)");
+        assert_eq!(
+            parse_body("f = g p { a = 1, b = x };"),
+            "(g p { a = 1, b = x })"
+        );
+        assert_eq!(parse_body("f = [x..y];"), "((prelude$enumFromTo x) y)");
+        let decl = parse_decl("f P { a = x, b = 1 } = x;");
+        assert!(
+            matches!(&decl.patterns[..], [Predicate::Record(ctor, fields)] if ctor.name() == "P" && fields.len() == 2)
+        );
+        let (_, items) = program_parser(new_span(
+            "data P = P { a :: Int, b :: List a } | Q;",
+            "<test>",
+        ))
+        .unwrap();
+        let [Item::Data(data_decl)] = &items[..] else {
+            panic!("expected a data declaration, got {items:?}");
+        };
+        let field_names: Vec<&str> = data_decl.ctors[0]
+            .field_names
+            .iter()
+            .map(|name| name.name())
+            .collect();
+        assert_eq!(field_names, ["a", "b"]);
+        assert_eq!(data_decl.ctors[0].fields.len(), 2);
+        assert!(data_decl.ctors[1].field_names.is_empty());
+    }
+
+    #[test]
+    fn test_patterns() {
+        let patterns = |src| {
+            parse_decl(src)
+                .patterns
+                .iter()
+                .map(|predicate| format!("{predicate:?}"))
+                .collect::<Vec<_>>()
+        };
+        let [wildcard, as_pattern, lazy] = &patterns("f _ xs@(x:_) ~(a, b) = 1;")[..] else {
+            panic!("expected three patterns");
+        };
+        assert!(wildcard.starts_with("Wildcard"));
+        assert!(as_pattern.starts_with("As(Id { token: Token { text: \"xs\""));
+        assert!(lazy.starts_with("Lazy(Tuple(["));
+        let decl = parse_decl("f [a] 'c' \"s\" (- 1) () = 1;");
+        let [list, char, string, negative, unit] = &decl.patterns[..] else {
+            panic!("expected five patterns");
+        };
+        let Predicate::Ctor(cons, args) = list else {
+            panic!("expected a constructor, got {list:?}");
+        };
+        assert_eq!(cons.name(), "Cons");
+        assert_eq!(args.len(), 2);
+        assert!(matches!(char, Predicate::Char('c', _)));
+        assert!(matches!(string, Predicate::Str(s, _) if s == "s"));
+        assert!(matches!(negative, Predicate::Int(-1, _)));
+        assert_eq!(negative.location().col, 16);
+        assert_eq!(unit.location().col, 21);
+        let decl = parse_decl("f 99999999999999999999 (-9223372036854775808) = 1;");
+        let [big, min] = &decl.patterns[..] else {
+            panic!("expected two patterns");
+        };
+        assert!(matches!(big, Predicate::BigInt(x, _) if x.to_string() == "99999999999999999999"));
+        assert!(matches!(min, Predicate::Int(i64::MIN, _)));
+    }
+
+    #[test]
+    fn test_guards() {
+        assert_eq!(
+            parse_body("f x | g x = 1 | otherwise = 2;"),
+            "| (g x) = 1 | otherwise = 2"
+        );
+        assert_eq!(
+            parse_body("f x\n  | p = a\n  | q = b\n  where p = x;"),
+            "let p = x : | p = a | q = b"
+        );
+        let body = parse_decl("f x = match x :\n  y | p y -> 1\n    | q y -> 2\n  _ -> 3;").body;
+        let Value::Match { pattern_exprs, .. } = body else {
+            panic!("expected a match, got {body:?}");
+        };
+        assert_eq!(pattern_exprs.len(), 2);
+        assert_eq!(
+            format!("{:?}", pattern_exprs[0].expr),
+            "| (p y) = 1 | (q y) = 2"
+        );
+    }
+
+    #[test]
+    fn test_if() {
+        let body = parse_decl("f x = if g x then 1 else 2;").body;
+        let Value::Match {
+            subject,
+            pattern_exprs,
+            ..
+        } = body
+        else {
+            panic!("expected a match, got {body:?}");
+        };
+        assert_eq!(format!("{subject:?}"), "(g x)");
+        assert_eq!(pattern_exprs.len(), 2);
+    }
+
+    #[test]
+    fn test_match() {
+        let body = parse_decl("f x = g match x : 1 -> (\"one\") y -> (h y);").body;
+        let Value::Callsite { function, argument } = body else {
+            panic!("expected a callsite, got {body:?}");
+        };
+        assert_eq!(format!("{function:?}"), "g");
+        assert!(
+            matches!(*argument, Value::Match { ref pattern_exprs, .. } if pattern_exprs.len() == 2)
+        );
+    }
+
+    #[test]
+    fn test_string_literals() {
+        assert_eq!(parse_body("f = g \"a\" \"b\";"), "((g \"a\") \"b\")");
+        assert_eq!(parse_body("f = \"abc\";"), "\"abc\"");
+        assert_eq!(parse_body("f = \"a\\\\b\\x41\";"), "\"a\\bA\"");
+        assert_eq!(parse_body(r##"f = r#"\""#;"##), r#""\\"""#);
+        assert_eq!(
+            parse_body("f = \"\"\"\n  a\n   b\n  \"\"\";"),
+            "\"a\\x0a b\""
+        );
+        assert!(decl_parser(new_span("f = \"\\q\";", "<test>")).is_err());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert_eq!(
+            parse_body(r#"f = "a ${x} b${show (n + 1)}";"#),
+            r#"((<> "a ") ((<> x) ((<> " b") (show {n + 1}))))"#
+        );
+        assert_eq!(parse_body(r#"f = "${x}";"#), r#"((<> "") x)"#);
+        assert_eq!(parse_body(r#"f = "$x \${x}";"#), r#""$x ${x}""#);
+        assert!(decl_parser(new_span(r#"f = "${x";"#, "<test>")).is_err());
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(parse_body("f = [];"), "Nil");
+        assert_eq!(parse_body("f = [1, g 2];"), "((Cons 1) ((Cons (g 2)) Nil))");
+        assert_eq!(parse_body("f = 1 : g 2 : xs;"), "{1 Cons (g 2) Cons xs}");
+        assert_eq!(parse_body("f = [1..];"), "(prelude$enumFrom 1)");
+        assert_eq!(parse_body("f = [1..n];"), "((prelude$enumFromTo 1) n)");
+        assert_eq!(parse_body("f = [1, 3..];"), "((prelude$enumFromThen 1) 3)");
+        assert_eq!(
+            parse_body("f = [1, 3..n];"),
+            "(((prelude$enumFromThenTo 1) 3) n)"
+        );
+        assert!(decl_parser(new_span("f = [1, 2, 3..];", "<test>")).is_err());
+    }
+
+    #[test]
+    fn test_cons_ends_match_subjects_and_let_bindings() {
+        assert_eq!(
+            parse_body("f = let x = 1 : x : Nil;"),
+            "let x = 1 : {x Cons Nil}"
+        );
+        let body = parse_decl("f xs = match x : y -> (y : (x : xs));").body;
+        let Value::Match {
+            subject,
+            pattern_exprs,
+            ..
+        } = body
+        else {
+            panic!("expected a match, got {body:?}");
+        };
+        assert_eq!(format!("{subject:?}"), "x");
+        assert_eq!(
+            format!("{:?}", pattern_exprs[0].expr),
+            "{y Cons {x Cons xs}}"
+        );
+    }
+
+    #[test]
+    fn test_comprehensions() {
+        assert_eq!(
+            parse_body("f = [x | x <- xs];"),
+            "((prelude$concatMap λx.((Cons x) Nil)) xs)"
+        );
+        let body = parse_body("f = [x | Just x <- xs, p x];");
+        assert!(body.starts_with("((prelude$concatMap λ"), "{body}");
+        assert!(body.ends_with(" xs)"), "{body}");
+    }
+
+    #[test]
+    fn test_number_literals() {
+        assert_eq!(
+            parse_body("f = g 0xff 0o17 0b101 1_000;"),
+            "((((g 255) 15) 5) 1000)"
+        );
+        assert_eq!(
+            parse_body("f = g 1.5 1e3 2.5E-2;"),
+            "(((g 1.5) 1000.0) 0.025)"
+        );
+        assert_eq!(
+            parse_body("f = 123_456_789_012_345_678_901;"),
+            "123456789012345678901"
+        );
+        assert_eq!(parse_body("f = g 'a' '\\n';"), "((g 'a') '\\n')");
+        // A dot which is not followed by digits is an operator.
+        assert_eq!(parse_body("f = 1.x;"), "{1 . x}");
+    }
 }
@@ -0,0 +1,285 @@
+use crate::{
+    decl::Binding,
+    error::{error, PitaError},
+    id::{gensym, Id},
+    location::Location,
+    value::{CtorDecl, DataDecl, PatternExpr, Predicate, Value},
+};
+
+/// Rewrite every field access, record update and record pattern in `bindings` in terms of
+/// positional constructors, now that the fields of every constructor are known.
+pub(crate) fn resolve_records(
+    data_decls: &[DataDecl],
+    bindings: &mut [Binding],
+) -> Result<(), PitaError> {
+    let resolver = Resolver {
+        data_decls,
+        ctors: data_decls
+            .iter()
+            .flat_map(|data_decl| &data_decl.ctors)
+            .collect(),
+    };
+    for (_, expr) in bindings {
+        resolver.resolve(expr)?;
+    }
+    Ok(())
+}
+
+struct Resolver<'a> {
+    data_decls: &'a [DataDecl],
+    /// Every constructor, in the order in which they are declared.
+    ctors: Vec<&'a CtorDecl>,
+}
+
+impl Resolver<'_> {
+    /// Replace every `Value::FieldAccess`, `Value::RecordUpdate` and `Predicate::Record` within
+    /// `expr`, including `expr` itself.
+    fn resolve(&self, expr: &mut Value) -> Result<(), PitaError> {
+        match expr {
+            Value::FieldAccess { record, field } => {
+                self.resolve(record)?;
+                let Some(data_decl) = self.data_decls.iter().find(|data_decl| {
+                    data_decl
+                        .field_names()
+                        .iter()
+                        .any(|name| name.name() == field.name())
+                }) else {
+                    return Err(error!(
+                        "{}: no constructor has the field {field}",
+                        field.location()
+                    ));
+                };
+                *expr = Value::Match {
+                    subject: Box::new(std::mem::replace(record, Value::Null)),
+                    pattern_exprs: data_decl.field_clauses(field),
+                    location: field.location(),
+                };
+            }
+            Value::RecordUpdate {
+                record,
+                fields,
+                location,
+            } => {
+                self.resolve(record)?;
+                for (_, value) in fields.iter_mut() {
+                    self.resolve(value)?;
+                }
+                let fields = std::mem::take(fields);
+                *expr = match &**record {
+                    Value::Id(ctor) if self.ctor(ctor).is_ok() => self.construct(ctor, fields)?,
+                    _ => self.update(std::mem::replace(record, Value::Null), fields, *location)?,
+                };
+            }
+            Value::Lambda { body, .. } => self.resolve(body)?,
+            Value::Match {
+                subject,
+                pattern_exprs,
+                ..
+            } => {
+                self.resolve(subject)?;
+                for pattern_expr in pattern_exprs {
+                    self.resolve_predicate(&mut pattern_expr.predicate)?;
+                    self.resolve(&mut pattern_expr.expr)?;
+                }
+            }
+            Value::Callsite { function, argument } => {
+                self.resolve(function)?;
+                self.resolve(argument)?;
+            }
+            Value::Guarded { alternatives, .. } => {
+                for (condition, body) in alternatives {
+                    self.resolve(condition)?;
+                    self.resolve(body)?;
+                }
+            }
+            Value::Tuple { dims } => {
+                for dim in dims {
+                    self.resolve(dim)?;
+                }
+            }
+            Value::Let { bindings, body } => {
+                for (_, expr) in bindings {
+                    self.resolve(expr)?;
+                }
+                self.resolve(body)?;
+            }
+            Value::Infix { operands, .. } => {
+                for operand in operands {
+                    self.resolve(operand)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn resolve_predicate(&self, predicate: &mut Predicate) -> Result<(), PitaError> {
+        match predicate {
+            Predicate::Record(ctor, fields) => {
+                let ctor_decl = self.ctor(ctor)?;
+                let mut predicates: Vec<Option<Predicate>> = vec![None; ctor_decl.fields.len()];
+                for (name, mut field) in std::mem::take(fields) {
+                    self.resolve_predicate(&mut field)?;
+                    let index = field_index(ctor_decl, &name)?;
+                    if predicates[index].replace(field).is_some() {
+                        return Err(error!(
+                            "{}: field {name} is matched more than once",
+                            name.location()
+                        ));
+                    }
+                }
+                let predicates = predicates
+                    .into_iter()
+                    .map(|field| field.unwrap_or(Predicate::Wildcard(ctor.location())))
+                    .collect();
+                *predicate = Predicate::Ctor(ctor.clone(), predicates);
+            }
+            Predicate::Tuple(predicates, _) | Predicate::Ctor(_, predicates) => {
+                for predicate in predicates {
+                    self.resolve_predicate(predicate)?;
+                }
+            }
+            Predicate::As(_, predicate) | Predicate::Lazy(predicate, _) => {
+                self.resolve_predicate(predicate)?;
+            }
+            Predicate::Irrefutable(_)
+            | Predicate::Wildcard(_)
+            | Predicate::Int(..)
+            | Predicate::BigInt(..)
+            | Predicate::Str(..)
+            | Predicate::Char(..) => {}
+        }
+        Ok(())
+    }
+
+    fn ctor(&self, ctor: &Id) -> Result<&CtorDecl, PitaError> {
+        self.ctors
+            .iter()
+            .copied()
+            .find(|ctor_decl| ctor_decl.name.name() == ctor.name())
+            .ok_or_else(|| error!("{}: unknown constructor {ctor}", ctor.location()))
+    }
+
+    /// Build `Person { name = value, ... }`, which must give every field of `Person`, as the
+    /// positional `Person value ...`.
+    fn construct(&self, ctor: &Id, fields: Vec<(Id, Value)>) -> Result<Value, PitaError> {
+        let ctor_decl = self.ctor(ctor)?;
+        let mut values: Vec<Option<Value>> = vec![None; ctor_decl.fields.len()];
+        for (name, value) in fields {
+            let index = field_index(ctor_decl, &name)?;
+            if values[index].replace(value).is_some() {
+                return Err(error!(
+                    "{}: field {name} is given more than once",
+                    name.location()
+                ));
+            }
+        }
+        values.into_iter().enumerate().try_fold(
+            Value::Id(ctor.clone()),
+            |function, (index, value)| {
+                let Some(value) = value else {
+                    return Err(match ctor_decl.field_names.get(index) {
+                        Some(name) => {
+                            error!("{}: field {name} of {ctor} is not given", ctor.location())
+                        }
+                        None => error!("{}: {ctor} has no named fields", ctor.location()),
+                    });
+                };
+                Ok(Value::Callsite {
+                    function: Box::new(function),
+                    argument: Box::new(value),
+                })
+            },
+        )
+    }
+
+    /// Build `record { name = value, ... }` as a match on each constructor which has all of the
+    /// given fields, which rebuilds `record` with those fields replaced.
+    fn update(
+        &self,
+        record: Value,
+        fields: Vec<(Id, Value)>,
+        location: Location,
+    ) -> Result<Value, PitaError> {
+        for (i, (name, _)) in fields.iter().enumerate() {
+            if fields[..i]
+                .iter()
+                .any(|(seen, _)| seen.name() == name.name())
+            {
+                return Err(error!(
+                    "{}: field {name} is given more than once",
+                    name.location()
+                ));
+            }
+        }
+        let ctors: Vec<&CtorDecl> = self
+            .ctors
+            .iter()
+            .copied()
+            .filter(|ctor_decl| {
+                fields
+                    .iter()
+                    .all(|(name, _)| field_index(ctor_decl, name).is_ok())
+            })
+            .collect();
+        if ctors.is_empty() {
+            let names: Vec<String> = fields.iter().map(|(name, _)| name.to_string()).collect();
+            return Err(error!(
+                "{location}: no constructor has the fields {}",
+                names.join(", ")
+            ));
+        }
+        let pattern_exprs = ctors
+            .into_iter()
+            .map(|ctor_decl| {
+                let params: Vec<Id> = ctor_decl
+                    .field_names
+                    .iter()
+                    .map(|name| gensym(name.location()))
+                    .collect();
+                let args = ctor_decl
+                    .field_names
+                    .iter()
+                    .zip(&params)
+                    .map(|(name, param)| {
+                        fields
+                            .iter()
+                            .find(|(field, _)| field.name() == name.name())
+                            .map_or_else(|| Value::Id(param.clone()), |(_, value)| value.clone())
+                    });
+                PatternExpr {
+                    predicate: Predicate::Ctor(
+                        ctor_decl.name.clone(),
+                        params.iter().cloned().map(Predicate::Irrefutable).collect(),
+                    ),
+                    expr: args.fold(Value::Id(ctor_decl.name.clone()), |function, arg| {
+                        Value::Callsite {
+                            function: Box::new(function),
+                            argument: Box::new(arg),
+                        }
+                    }),
+                }
+            })
+            .collect();
+        Ok(Value::Match {
+            subject: Box::new(record),
+            pattern_exprs,
+            location,
+        })
+    }
+}
+
+/// The position of the field `name` among the arguments of `ctor_decl`.
+fn field_index(ctor_decl: &CtorDecl, name: &Id) -> Result<usize, PitaError> {
+    ctor_decl
+        .field_names
+        .iter()
+        .position(|field_name| field_name.name() == name.name())
+        .ok_or_else(|| {
+            error!(
+                "{}: {} has no field {name}",
+                name.location(),
+                ctor_decl.name
+            )
+        })
+}
@@ -1,13 +1,29 @@
 #![allow(dead_code)]
-use crate::id::Id;
+use crate::{id::Id, location::Location, value::Value};
 
 #[derive(Debug)]
 pub enum RuntimeError {
     UnresolvedSymbol(Id),
     InvalidDecl(String),
     InvalidCallsite(String),
-    NoMatch(String),
+    NoMatch {
+        subject: Value,
+        location: Location,
+    },
     MatchTypeError(String),
+    InfiniteLoop(Location),
+    /// Every guard of a definition which is not part of a match was `False`.
+    NoGuard(Location),
+    /// An arithmetic operation has no result, such as a division by zero.
+    Arithmetic(String),
+    /// An expression which cannot be evaluated, such as syntax which should have been resolved
+    /// before the program ran.
+    InvalidExpr(String),
+    /// A field accessor was applied to a constructor which lacks the field.
+    NoField {
+        ctor: Id,
+        field: Id,
+    },
 }
 
 impl std::fmt::Display for RuntimeError {
@@ -22,10 +38,37 @@ impl std::fmt::Display for RuntimeError {
             RuntimeError::InvalidCallsite(msg) => {
                 write!(f, "pita runtime error: invalid callsite: {msg}")
             }
-            RuntimeError::NoMatch(msg) => write!(f, "pita runtime error: no match: {msg}"),
+            RuntimeError::NoMatch { subject, location } => {
+                write!(
+                    f,
+                    "{location}: pita runtime error: no match for {subject:?}"
+                )
+            }
             RuntimeError::MatchTypeError(msg) => {
                 write!(f, "pita runtime error: match type error: {msg}")
             }
+            RuntimeError::InfiniteLoop(location) => {
+                write!(
+                    f,
+                    "{location}: pita runtime error: infinite loop: value depends on itself"
+                )
+            }
+            RuntimeError::NoGuard(location) => {
+                write!(f, "{location}: pita runtime error: no guard is true")
+            }
+            RuntimeError::Arithmetic(msg) => {
+                write!(f, "pita runtime error: arithmetic error: {msg}")
+            }
+            RuntimeError::InvalidExpr(msg) => {
+                write!(f, "pita runtime error: cannot evaluate {msg}")
+            }
+            RuntimeError::NoField { ctor, field } => {
+                write!(
+                    f,
+                    "{}: pita runtime error: {ctor} has no field {field}",
+                    field.location()
+                )
+            }
         }
     }
 }
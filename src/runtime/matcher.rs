@@ -0,0 +1,189 @@
+use crate::{
+    env::Env,
+    id::gensym,
+    location::Location,
+    runtime::error::RuntimeError,
+    value::{PatternExpr, Predicate, Value},
+};
+
+/// An in-progress `match`. Clauses are tried in source order, and the subject is only evaluated
+/// as far as the predicates of each clause demand.
+pub(crate) struct Matcher {
+    env: Env,
+    subject: Value,
+    pattern_exprs: Vec<PatternExpr>,
+    location: Location,
+    clause: usize,
+    bindings: Env,
+    work: Vec<(Predicate, Value)>,
+}
+
+pub(crate) enum MatchStep {
+    /// A clause matched, and its expression should be evaluated in `env`. If the expression has
+    /// guards, `fall_through` resumes the match at the next clause once they all fail.
+    Matched {
+        env: Env,
+        expr: Value,
+        fall_through: Option<Box<Matcher>>,
+    },
+    /// `value` must be evaluated to WHNF before `predicate` can be checked against it.
+    Force {
+        matcher: Box<Matcher>,
+        predicate: Predicate,
+        value: Value,
+    },
+}
+
+impl Matcher {
+    pub fn new(
+        env: Env,
+        subject: Value,
+        pattern_exprs: Vec<PatternExpr>,
+        location: Location,
+    ) -> Result<Self, RuntimeError> {
+        let Some(first) = pattern_exprs.first() else {
+            return Err(RuntimeError::NoMatch { subject, location });
+        };
+        Ok(Self {
+            work: vec![(first.predicate.clone(), subject.clone())],
+            bindings: env.clone(),
+            env,
+            subject,
+            pattern_exprs,
+            location,
+            clause: 0,
+        })
+    }
+
+    /// Continue matching once the value demanded by a `MatchStep::Force` is in WHNF.
+    pub fn resume(mut self, predicate: Predicate, value: Value) -> Result<MatchStep, RuntimeError> {
+        self.work.push((predicate, value));
+        self.step()
+    }
+
+    pub fn step(mut self) -> Result<MatchStep, RuntimeError> {
+        loop {
+            let Some((predicate, value)) = self.work.pop() else {
+                let expr = &self.pattern_exprs[self.clause].expr;
+                if expr.is_guarded() {
+                    return Ok(MatchStep::Matched {
+                        env: self.bindings.clone(),
+                        expr: expr.clone(),
+                        fall_through: Some(Box::new(self)),
+                    });
+                }
+                let expr = self
+                    .pattern_exprs
+                    .into_iter()
+                    .nth(self.clause)
+                    .expect("current clause exists")
+                    .expr;
+                return Ok(MatchStep::Matched {
+                    env: self.bindings,
+                    expr,
+                    fall_through: None,
+                });
+            };
+            let matched = match (predicate, value) {
+                (Predicate::Irrefutable(id), value) => {
+                    self.bindings.add_symbol_mut(id, value);
+                    true
+                }
+                (Predicate::Wildcard(_), _) => true,
+                (Predicate::As(id, predicate), value) => {
+                    self.bindings.add_symbol_mut(id, value.clone());
+                    self.work.push((*predicate, value));
+                    true
+                }
+                (Predicate::Lazy(predicate, location), value) => {
+                    self.bind_lazily(*predicate, value, location);
+                    true
+                }
+                (predicate, value) if !value.is_weak_head_normal_form() => {
+                    return Ok(MatchStep::Force {
+                        matcher: Box::new(self),
+                        predicate,
+                        value,
+                    });
+                }
+                (Predicate::Int(expected, _), Value::Int(actual)) => expected == actual,
+                (Predicate::BigInt(expected, _), Value::BigInt(actual)) => expected == actual,
+                // Integers are only big when they do not fit in an `i64`.
+                (Predicate::Int(..), Value::BigInt(_)) | (Predicate::BigInt(..), Value::Int(_)) => {
+                    false
+                }
+                (Predicate::Str(expected, _), Value::Str(actual)) => expected == actual,
+                (Predicate::Char(expected, _), Value::Char(actual)) => expected == actual,
+                (Predicate::Tuple(predicates, _), Value::Tuple { dims })
+                    if predicates.len() == dims.len() =>
+                {
+                    // Push in reverse so that dimensions are matched left to right.
+                    self.work.extend(predicates.into_iter().zip(dims).rev());
+                    true
+                }
+                (Predicate::Ctor(ctor, predicates), Value::Ctor { name, dims }) => {
+                    if ctor.name() != name.name() {
+                        false
+                    } else if predicates.len() != dims.len() {
+                        return Err(RuntimeError::MatchTypeError(format!(
+                            "{}: constructor {ctor} expects {} arguments, got {}",
+                            ctor.location(),
+                            dims.len(),
+                            predicates.len()
+                        )));
+                    } else {
+                        self.work.extend(predicates.into_iter().zip(dims).rev());
+                        true
+                    }
+                }
+                (predicate, value) => {
+                    return Err(RuntimeError::MatchTypeError(format!(
+                        "{}: cannot match {value:?} against {predicate:?}",
+                        self.location
+                    )));
+                }
+            };
+            if !matched {
+                self.next_clause()?;
+            }
+        }
+    }
+
+    /// Bind each name in `predicate` to a thunk which matches `value` against `predicate` and
+    /// yields that name, so that `value` is only examined once one of the names is demanded.
+    fn bind_lazily(&mut self, predicate: Predicate, value: Value, location: Location) {
+        let subject = gensym(location);
+        let env = Env::new().add_symbol(subject.clone(), value);
+        for id in predicate.bindings() {
+            let projection = Value::Match {
+                subject: Box::new(Value::Id(subject.clone())),
+                pattern_exprs: vec![PatternExpr {
+                    predicate: predicate.clone(),
+                    expr: Value::Id(id.clone()),
+                }],
+                location,
+            };
+            self.bindings
+                .add_symbol_mut(id.clone(), Value::thunk(env.clone(), projection, location));
+        }
+    }
+
+    /// Continue with the next clause, since every guard of the matched clause failed.
+    pub fn fall_through(mut self) -> Result<MatchStep, RuntimeError> {
+        self.next_clause()?;
+        self.step()
+    }
+
+    fn next_clause(&mut self) -> Result<(), RuntimeError> {
+        self.clause += 1;
+        let Some(pattern_expr) = self.pattern_exprs.get(self.clause) else {
+            return Err(RuntimeError::NoMatch {
+                subject: self.subject.clone(),
+                location: self.location,
+            });
+        };
+        self.work = vec![(pattern_expr.predicate.clone(), self.subject.clone())];
+        self.bindings = self.env.clone();
+        Ok(())
+    }
+}
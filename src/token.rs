@@ -1,6 +1,4 @@
-use nom_locate::LocatedSpan;
-
-use crate::location::{Location, LocationFilename};
+use crate::{location::Location, parser::Span};
 
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -8,8 +6,8 @@ pub struct Token {
     pub location: Location,
 }
 
-impl From<LocatedSpan<&str, LocationFilename>> for Token {
-    fn from(span: LocatedSpan<&str, LocationFilename>) -> Self {
+impl From<Span<'_>> for Token {
+    fn from(span: Span<'_>) -> Self {
         Self {
             text: span.fragment().to_string(),
             location: (&span).into(),
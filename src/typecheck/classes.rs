@@ -0,0 +1,291 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    decl::{build_bindings, build_lambdas, Binding},
+    env::Env,
+    error::{error, PitaError},
+    id::{derived_id, gensym, Id},
+    value::{ClassDecl, InstanceDecl, PatternExpr, Predicate, Value},
+};
+
+// A dictionary of a class is a tuple of the dictionaries of its superclasses, followed by its
+// methods, in the order in which they are declared. Each method is a global binding which
+// selects its field from the dictionary it is passed, and the instance it came from, along with
+// any default definitions of methods, are bindings with the names below.
+
+/// The name of the dictionary of the instance of `class` for `type_name`.
+pub(crate) fn dictionary_name(class: &str, type_name: &str) -> String {
+    format!("{class}${type_name}")
+}
+
+/// The name of the definition of `method` in the instance of `class` for `type_name`.
+pub(crate) fn instance_method_name(class: &str, type_name: &str, method: &str) -> String {
+    format!("{class}${type_name}${method}")
+}
+
+/// The name of the default definition of `method` in `class`.
+pub(crate) fn default_method_name(class: &str, method: &str) -> String {
+    format!("{class}$default${method}")
+}
+
+/// The name of the binding which selects the dictionary of `superclass` from one of `class`.
+pub(crate) fn superclass_name(class: &str, superclass: &str) -> String {
+    format!("{class}$super${superclass}")
+}
+
+/// Check the class and instance declarations of a program, and build the definitions of the
+/// methods of each instance, along with the default definitions of each class, as bindings
+/// named for their class and instance.
+pub(crate) fn method_bindings(
+    env: &Env,
+    class_decls: &[ClassDecl],
+    instance_decls: &[InstanceDecl],
+    bindings: &[Binding],
+) -> Result<Vec<Binding>, PitaError> {
+    let mut classes: HashMap<&str, &ClassDecl> = HashMap::new();
+    let mut methods: HashMap<&str, &Id> = HashMap::new();
+    for class_decl in class_decls {
+        let class = &class_decl.name;
+        if let Some(existing) = classes.insert(class.name(), class_decl) {
+            return Err(error!(
+                "{}: class {class} is already defined at {}",
+                class.location(),
+                existing.name.location()
+            ));
+        }
+        for method in &class_decl.methods {
+            let name = &method.name;
+            if let Some(existing) = methods.insert(name.name(), class) {
+                return Err(error!(
+                    "{}: {name} is already a method of class {existing}",
+                    name.location()
+                ));
+            }
+            if env.has_symbol(name.name()) {
+                return Err(error!("{}: {name} is already a builtin", name.location()));
+            }
+            if let Some((existing, _)) = bindings
+                .iter()
+                .find(|(binding, _)| binding.name() == name.name())
+            {
+                return Err(error!(
+                    "{}: {name} is already defined as a method of class {class} at {}",
+                    existing.location(),
+                    name.location()
+                ));
+            }
+        }
+    }
+    let mut method_bindings = Vec::new();
+    for class_decl in class_decls {
+        let class = &class_decl.name;
+        for superclass in &class_decl.superclasses {
+            if !classes.contains_key(superclass.class.name()) {
+                return Err(error!(
+                    "{}: unknown class: {}",
+                    superclass.class.location(),
+                    superclass.class
+                ));
+            }
+            if superclass.var.name() != class_decl.var.name() {
+                return Err(error!(
+                    "{}: the superclasses of {class} may only constrain {}",
+                    superclass.var.location(),
+                    class_decl.var
+                ));
+            }
+        }
+        check_acyclic(class_decl, &classes, &mut Vec::new())?;
+        for (name, value) in build_bindings(class_decl.defaults.clone())? {
+            if !is_method(class_decl, &name) {
+                return Err(error!(
+                    "{}: {name} is not a method of class {class}",
+                    name.location()
+                ));
+            }
+            let default = default_method_name(class.name(), name.name());
+            method_bindings.push((derived_id(&default, name.location()), value));
+        }
+    }
+    let mut instances = HashSet::new();
+    for instance in instance_decls {
+        let class = &instance.class;
+        let type_name = &instance.type_name;
+        let Some(class_decl) = classes.get(class.name()) else {
+            return Err(error!("{}: unknown class: {class}", class.location()));
+        };
+        if !instances.insert((class.name(), type_name)) {
+            return Err(error!(
+                "{}: there is already an instance of {class} for {type_name}",
+                class.location()
+            ));
+        }
+        for (i, param) in instance.params.iter().enumerate() {
+            if instance.params[..i]
+                .iter()
+                .any(|other| other.name() == param.name())
+            {
+                return Err(error!(
+                    "{}: type variable {param} appears more than once in an instance type",
+                    param.location()
+                ));
+            }
+        }
+        for constraint in &instance.context {
+            if !classes.contains_key(constraint.class.name()) {
+                return Err(error!(
+                    "{}: unknown class: {}",
+                    constraint.class.location(),
+                    constraint.class
+                ));
+            }
+            if !instance
+                .params
+                .iter()
+                .any(|param| param.name() == constraint.var.name())
+            {
+                return Err(error!(
+                    "{}: type variable {} is not a parameter of the instance type",
+                    constraint.var.location(),
+                    constraint.var
+                ));
+            }
+        }
+        for (name, value) in build_bindings(instance.decls.clone())? {
+            if !is_method(class_decl, &name) {
+                return Err(error!(
+                    "{}: {name} is not a method of class {class}",
+                    name.location()
+                ));
+            }
+            let method = instance_method_name(class.name(), type_name, name.name());
+            method_bindings.push((derived_id(&method, name.location()), value));
+        }
+        for method in &class_decl.methods {
+            let name = method.name.name();
+            let defined = instance.decls.iter().any(|decl| decl.name.name() == name);
+            let defaulted = class_decl
+                .defaults
+                .iter()
+                .any(|decl| decl.name.name() == name);
+            if !defined && !defaulted {
+                return Err(error!(
+                    "{}: the instance of {class} for {type_name} lacks a definition of {name}",
+                    class.location()
+                ));
+            }
+        }
+    }
+    Ok(method_bindings)
+}
+
+fn is_method(class_decl: &ClassDecl, name: &Id) -> bool {
+    class_decl
+        .methods
+        .iter()
+        .any(|method| method.name.name() == name.name())
+}
+
+/// Check that `class_decl` is not its own superclass, however indirectly. `path` holds the
+/// classes which led to it.
+fn check_acyclic<'a>(
+    class_decl: &'a ClassDecl,
+    classes: &HashMap<&str, &'a ClassDecl>,
+    path: &mut Vec<&'a str>,
+) -> Result<(), PitaError> {
+    let class = class_decl.name.name();
+    if path.contains(&class) {
+        return Err(error!(
+            "{}: class {class} is its own superclass",
+            class_decl.name.location()
+        ));
+    }
+    path.push(class);
+    for superclass in &class_decl.superclasses {
+        check_acyclic(classes[superclass.class.name()], classes, path)?;
+    }
+    path.pop();
+    Ok(())
+}
+
+/// Build the bindings which select each field of a dictionary of `class_decl`: a binding for
+/// each superclass, and each method.
+pub(crate) fn selector_bindings(class_decl: &ClassDecl) -> Vec<Binding> {
+    let class = class_decl.name.name();
+    let location = class_decl.name.location();
+    let names: Vec<Id> = class_decl
+        .superclasses
+        .iter()
+        .map(|superclass| {
+            let name = superclass_name(class, superclass.class.name());
+            derived_id(&name, superclass.class.location())
+        })
+        .chain(class_decl.methods.iter().map(|method| method.name.clone()))
+        .collect();
+    names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let dictionary = gensym(location);
+            let field = gensym(location);
+            let predicates = (0..names.len())
+                .map(|i| {
+                    if i == index {
+                        Predicate::Irrefutable(field.clone())
+                    } else {
+                        Predicate::Wildcard(location)
+                    }
+                })
+                .collect();
+            let selector = Value::Match {
+                subject: Box::new(Value::Id(dictionary.clone())),
+                pattern_exprs: vec![PatternExpr {
+                    predicate: Predicate::Tuple(predicates, location),
+                    expr: Value::Id(field),
+                }],
+                location,
+            };
+            (name.clone(), build_lambdas(vec![dictionary], selector))
+        })
+        .collect()
+}
+
+/// Build the dictionary of `instance`, which takes `context`, the dictionaries its context
+/// requires, and holds `superclasses`, the dictionaries of the superclasses of its class. A
+/// method which the instance does not define is the default definition, passed this same
+/// dictionary.
+pub(crate) fn dictionary_binding(
+    class_decl: &ClassDecl,
+    instance: &InstanceDecl,
+    context: Vec<Id>,
+    superclasses: Vec<Value>,
+) -> Binding {
+    let class = instance.class.name();
+    let location = instance.class.location();
+    let name = derived_id(&dictionary_name(class, &instance.type_name), location);
+    let args: Vec<Value> = context.iter().cloned().map(Value::Id).collect();
+    let dictionary = apply(Value::Id(name.clone()), &args);
+    let methods = class_decl.methods.iter().map(|method| {
+        let method = method.name.name();
+        if instance.decls.iter().any(|decl| decl.name.name() == method) {
+            let name = instance_method_name(class, &instance.type_name, method);
+            apply(Value::Id(derived_id(&name, location)), &args)
+        } else {
+            let name = default_method_name(class, method);
+            apply(
+                Value::Id(derived_id(&name, location)),
+                std::slice::from_ref(&dictionary),
+            )
+        }
+    });
+    let dims = superclasses.into_iter().chain(methods).collect();
+    (name, build_lambdas(context, Value::Tuple { dims }))
+}
+
+/// Build `function args...`.
+fn apply(function: Value, args: &[Value]) -> Value {
+    args.iter().fold(function, |function, arg| Value::Callsite {
+        function: Box::new(function),
+        argument: Box::new(arg.clone()),
+    })
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{id::Id, value::Value};
+
+/// Split recursive bindings into strongly connected groups, ordered so that every group comes
+/// after the groups it refers to. Each group can then be generalized before the groups which use
+/// it are inferred, which is what makes let-polymorphism work for recursive bindings.
+///
+/// References to names which have a type signature are not dependencies, since their type is
+/// known up front.
+pub(crate) fn binding_groups(
+    bindings: &[(Id, Value)],
+    is_signed: impl Fn(&str) -> bool,
+) -> Vec<Vec<usize>> {
+    let index: HashMap<&str, usize> = bindings
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.name(), i))
+        .collect();
+    let edges = bindings
+        .iter()
+        .map(|(_, expr)| {
+            let mut free = BTreeSet::new();
+            free_vars(expr, &mut Vec::new(), &mut free);
+            free.into_iter()
+                .filter(|name| !is_signed(name))
+                .filter_map(|name| index.get(name).copied())
+                .collect()
+        })
+        .collect();
+    let mut tarjan = Tarjan {
+        edges,
+        index: vec![None; bindings.len()],
+        lowlink: vec![0; bindings.len()],
+        on_stack: vec![false; bindings.len()],
+        stack: Vec::new(),
+        next_index: 0,
+        groups: Vec::new(),
+    };
+    for node in 0..bindings.len() {
+        if tarjan.index[node].is_none() {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.groups
+}
+
+/// Tarjan's algorithm emits each strongly connected component after every component reachable
+/// from it, which is exactly dependency order.
+struct Tarjan {
+    edges: Vec<Vec<usize>>,
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    groups: Vec<Vec<usize>>,
+}
+
+impl Tarjan {
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next_index);
+        self.lowlink[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+        for i in 0..self.edges[node].len() {
+            let next = self.edges[node][i];
+            match self.index[next] {
+                None => {
+                    self.visit(next);
+                    self.lowlink[node] = self.lowlink[node].min(self.lowlink[next]);
+                }
+                Some(index) if self.on_stack[next] => {
+                    self.lowlink[node] = self.lowlink[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+        if Some(self.lowlink[node]) == self.index[node] {
+            let mut group = Vec::new();
+            loop {
+                let member = self.stack.pop().expect("node is on the stack");
+                self.on_stack[member] = false;
+                group.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            group.sort_unstable();
+            self.groups.push(group);
+        }
+    }
+}
+
+/// Collect the names which `expr` refers to without binding them itself.
+fn free_vars<'a>(expr: &'a Value, bound: &mut Vec<&'a str>, free: &mut BTreeSet<&'a str>) {
+    match expr {
+        Value::Id(id) if !bound.contains(&id.name()) => {
+            free.insert(id.name());
+        }
+        Value::Lambda { param, body } => {
+            bound.push(param.name());
+            free_vars(body, bound, free);
+            bound.pop();
+        }
+        Value::Match {
+            subject,
+            pattern_exprs,
+            ..
+        } => {
+            free_vars(subject, bound, free);
+            for pattern_expr in pattern_exprs {
+                let scope = bound.len();
+                bound.extend(pattern_expr.predicate.bindings().map(Id::name));
+                free_vars(&pattern_expr.expr, bound, free);
+                bound.truncate(scope);
+            }
+        }
+        Value::Callsite { function, argument } => {
+            free_vars(function, bound, free);
+            free_vars(argument, bound, free);
+        }
+        Value::Guarded { alternatives, .. } => {
+            for (condition, body) in alternatives {
+                free_vars(condition, bound, free);
+                free_vars(body, bound, free);
+            }
+        }
+        Value::Tuple { dims } | Value::Ctor { dims, .. } => {
+            for dim in dims {
+                free_vars(dim, bound, free);
+            }
+        }
+        Value::Let { bindings, body } => {
+            let scope = bound.len();
+            bound.extend(bindings.iter().map(|(name, _)| name.name()));
+            for (_, expr) in bindings {
+                free_vars(expr, bound, free);
+            }
+            free_vars(body, bound, free);
+            bound.truncate(scope);
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{decl::Binding, typecheck::infer::Dictionaries, value::Value};
+
+/// Pass dictionaries explicitly throughout `bindings`. Type checking leaves each use of an
+/// overloaded binding applied to placeholders, each of which is replaced by the dictionaries it
+/// stands for.
+pub(crate) fn elaborate(bindings: &mut [Binding], dictionaries: &Dictionaries) {
+    for (_, expr) in bindings {
+        elaborate_expr(expr, dictionaries);
+    }
+}
+
+fn elaborate_expr(expr: &mut Value, dictionaries: &Dictionaries) {
+    match expr {
+        Value::Callsite { function, argument } => {
+            elaborate_expr(function, dictionaries);
+            let resolved = match &**argument {
+                Value::Id(placeholder) => dictionaries.get(placeholder.name()),
+                _ => None,
+            };
+            match resolved {
+                Some(resolved) => {
+                    let function = std::mem::replace(&mut **function, Value::Null);
+                    *expr = resolved.iter().fold(function, |function, dictionary| {
+                        // A dictionary may itself take dictionaries for the context of its
+                        // instance.
+                        let mut dictionary = dictionary.clone();
+                        elaborate_expr(&mut dictionary, dictionaries);
+                        Value::Callsite {
+                            function: Box::new(function),
+                            argument: Box::new(dictionary),
+                        }
+                    });
+                }
+                None => elaborate_expr(argument, dictionaries),
+            }
+        }
+        Value::Lambda { body, .. } => elaborate_expr(body, dictionaries),
+        Value::Match {
+            subject,
+            pattern_exprs,
+            ..
+        } => {
+            elaborate_expr(subject, dictionaries);
+            for pattern_expr in pattern_exprs {
+                elaborate_expr(&mut pattern_expr.expr, dictionaries);
+            }
+        }
+        Value::Guarded { alternatives, .. } => {
+            for (condition, body) in alternatives {
+                elaborate_expr(condition, dictionaries);
+                elaborate_expr(body, dictionaries);
+            }
+        }
+        Value::Tuple { dims } => {
+            for dim in dims {
+                elaborate_expr(dim, dictionaries);
+            }
+        }
+        Value::Let { bindings, body } => {
+            elaborate(bindings, dictionaries);
+            elaborate_expr(body, dictionaries);
+        }
+        _ => {}
+    }
+}
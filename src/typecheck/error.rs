@@ -0,0 +1,208 @@
+#![allow(dead_code)]
+use crate::{id::Id, location::Location};
+
+/// Types are rendered when the error is raised, since rendering requires the checker's
+/// substitution.
+#[derive(Debug)]
+pub enum TypeError {
+    UnresolvedSymbol(Id),
+    /// Two types which were required to be equal are not. Each side carries the site which gave
+    /// rise to it.
+    Mismatch {
+        expected: String,
+        expected_at: Location,
+        found: String,
+        found_at: Location,
+    },
+    /// A value of a non-function type was applied to an argument.
+    NotAFunction {
+        ty: String,
+        ty_at: Location,
+        location: Location,
+    },
+    /// The inferred type of `name` is not an instance of its declared type.
+    SignatureMismatch {
+        name: Id,
+        declared: String,
+        inferred: String,
+        location: Location,
+    },
+    InfiniteType {
+        var: String,
+        ty: String,
+        location: Location,
+    },
+    UnknownType(Id),
+    /// A data declaration redefines a builtin type such as `Int`.
+    BuiltinType(Id),
+    UnboundTypeVariable(Id),
+    TypeArity {
+        name: Id,
+        expected: usize,
+        found: usize,
+    },
+    CtorArity {
+        ctor: Id,
+        expected: usize,
+        found: usize,
+    },
+    UnknownClass(Id),
+    NoInstance {
+        class: String,
+        ty: String,
+        location: Location,
+    },
+    /// A constraint is on a type variable which nothing determines, so there is no telling which
+    /// instance it needs.
+    Ambiguous {
+        class: String,
+        location: Location,
+    },
+    UnknownInstanceType {
+        class: Id,
+        type_name: String,
+    },
+    InstanceArity {
+        class: Id,
+        type_name: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeError::UnresolvedSymbol(id) => {
+                write!(
+                    f,
+                    "{}: pita type error: unresolved symbol: {id}",
+                    id.location()
+                )
+            }
+            TypeError::Mismatch {
+                expected,
+                expected_at,
+                found,
+                found_at,
+            } => {
+                write!(
+                    f,
+                    "{found_at}: pita type error: expected {expected}, found {found}\n\
+                     {expected_at}: note: {expected} is required here"
+                )
+            }
+            TypeError::NotAFunction {
+                ty,
+                ty_at,
+                location,
+            } => {
+                write!(
+                    f,
+                    "{location}: pita type error: cannot apply a value of type {ty} as a function\n\
+                     {ty_at}: note: its type is {ty} because of this"
+                )
+            }
+            TypeError::SignatureMismatch {
+                name,
+                declared,
+                inferred,
+                location,
+            } => {
+                write!(
+                    f,
+                    "{location}: pita type error: {name} is declared as {declared}, but its \
+                     inferred type is {inferred}"
+                )
+            }
+            TypeError::InfiniteType { var, ty, location } => {
+                write!(
+                    f,
+                    "{location}: pita type error: infinite type: {var} occurs in {ty}"
+                )
+            }
+            TypeError::UnknownType(id) => {
+                write!(f, "{}: pita type error: unknown type: {id}", id.location())
+            }
+            TypeError::BuiltinType(id) => {
+                write!(
+                    f,
+                    "{}: pita type error: type {id} is a builtin type",
+                    id.location()
+                )
+            }
+            TypeError::UnboundTypeVariable(id) => {
+                write!(
+                    f,
+                    "{}: pita type error: type variable {id} is not in scope",
+                    id.location()
+                )
+            }
+            TypeError::TypeArity {
+                name,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "{}: pita type error: type {name} expects {expected} arguments, got {found}",
+                    name.location()
+                )
+            }
+            TypeError::CtorArity {
+                ctor,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "{}: pita type error: constructor {ctor} expects {expected} arguments, got \
+                     {found}",
+                    ctor.location()
+                )
+            }
+            TypeError::UnknownClass(id) => {
+                write!(f, "{}: pita type error: unknown class: {id}", id.location())
+            }
+            TypeError::NoInstance {
+                class,
+                ty,
+                location,
+            } => {
+                write!(
+                    f,
+                    "{location}: pita type error: no instance for {class} {ty}"
+                )
+            }
+            TypeError::Ambiguous { class, location } => {
+                write!(
+                    f,
+                    "{location}: pita type error: ambiguous type: nothing determines which \
+                     instance of {class} is needed here"
+                )
+            }
+            TypeError::UnknownInstanceType { class, type_name } => {
+                write!(
+                    f,
+                    "{}: pita type error: unknown type in instance of {class}: {type_name}",
+                    class.location()
+                )
+            }
+            TypeError::InstanceArity {
+                class,
+                type_name,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "{}: pita type error: type {type_name} expects at most {expected} \
+                     arguments in an instance of {class}, got {found}",
+                    class.location()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
+use num_traits::Signed;
+
+use crate::{
+    decl::Binding,
+    error::Warning,
+    location::Location,
+    typecheck::infer::known_or,
+    value::{DataDecl, PatternExpr, Predicate, Value},
+};
+
+/// Warn about every `match` in `bindings` which does not handle every possible value, and about
+/// clauses which can never be reached because earlier clauses already cover them. This runs
+/// after type checking, so every column of a match is known to hold values of one type.
+pub(crate) fn check_matches(data_decls: &[DataDecl], bindings: &[Binding]) -> Vec<Warning> {
+    let mut analysis = Analysis {
+        ctors: HashMap::new(),
+        types: HashMap::new(),
+        warnings: Vec::new(),
+    };
+    for data_decl in data_decls {
+        let name = data_decl.name.name().to_string();
+        for ctor in &data_decl.ctors {
+            analysis.ctors.insert(
+                ctor.name.name().to_string(),
+                (name.clone(), ctor.fields.len()),
+            );
+        }
+        analysis.types.insert(
+            name,
+            data_decl
+                .ctors
+                .iter()
+                .map(|ctor| ctor.name.name().to_string())
+                .collect(),
+        );
+    }
+    for (_, expr) in bindings {
+        analysis.visit(expr);
+    }
+    analysis.warnings
+}
+
+/// A predicate, simplified for analysis.
+#[derive(Debug, Clone)]
+enum Pat {
+    Wildcard,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// Literals and tuples are treated as constructors: literals have infinitely many nullary ones,
+/// and tuples have exactly one.
+#[derive(Debug, Clone, PartialEq)]
+enum Ctor {
+    Data(String),
+    Int(BigInt),
+    Str(String),
+    Char(char),
+    Tuple(usize),
+}
+
+impl From<&Predicate> for Pat {
+    fn from(predicate: &Predicate) -> Self {
+        match predicate {
+            // A lazy predicate always matches, and any failure happens later, when it is used.
+            Predicate::Irrefutable(_) | Predicate::Wildcard(_) | Predicate::Lazy(..) => {
+                Pat::Wildcard
+            }
+            Predicate::As(_, predicate) => Pat::from(&**predicate),
+            Predicate::Int(value, _) => Pat::Ctor(Ctor::Int(BigInt::from(*value)), Vec::new()),
+            Predicate::BigInt(value, _) => Pat::Ctor(Ctor::Int(value.clone()), Vec::new()),
+            Predicate::Str(value, _) => Pat::Ctor(Ctor::Str(value.clone()), Vec::new()),
+            Predicate::Char(value, _) => Pat::Ctor(Ctor::Char(*value), Vec::new()),
+            Predicate::Tuple(predicates, _) => Pat::Ctor(
+                Ctor::Tuple(predicates.len()),
+                predicates.iter().map(Pat::from).collect(),
+            ),
+            Predicate::Ctor(ctor, predicates) => Pat::Ctor(
+                Ctor::Data(ctor.name().to_string()),
+                predicates.iter().map(Pat::from).collect(),
+            ),
+            Predicate::Record(..) => unreachable!("{predicate:?} is resolved before checking"),
+        }
+    }
+}
+
+impl std::fmt::Display for Pat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pat::Wildcard => f.write_str("_"),
+            Pat::Ctor(Ctor::Int(value), _) => write!(f, "{value}"),
+            Pat::Ctor(Ctor::Str(value), _) => write!(f, "{value:?}"),
+            Pat::Ctor(Ctor::Char(value), _) => write!(f, "{value:?}"),
+            // A single parenthesized predicate is just grouping.
+            Pat::Ctor(Ctor::Tuple(1), args) => write!(f, "{}", args[0]),
+            Pat::Ctor(Ctor::Tuple(_), args) => {
+                f.write_str("(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                f.write_str(")")
+            }
+            Pat::Ctor(Ctor::Data(name), args) => {
+                f.write_str(name)?;
+                for arg in args {
+                    match arg {
+                        Pat::Ctor(Ctor::Data(_), args) if !args.is_empty() => {
+                            write!(f, " ({arg})")?
+                        }
+                        Pat::Ctor(Ctor::Int(value), _) if value.is_negative() => {
+                            write!(f, " ({arg})")?
+                        }
+                        arg => write!(f, " {arg}")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Analysis {
+    /// The data type and arity of each constructor.
+    ctors: HashMap<String, (String, usize)>,
+    /// The constructors of each data type, in declaration order.
+    types: HashMap<String, Vec<String>>,
+    warnings: Vec<Warning>,
+}
+
+impl Analysis {
+    fn visit(&mut self, expr: &Value) {
+        match expr {
+            Value::Match {
+                subject,
+                pattern_exprs,
+                location,
+            } => {
+                self.visit(subject);
+                self.check_match(pattern_exprs, *location);
+                for pattern_expr in pattern_exprs {
+                    self.visit(&pattern_expr.expr);
+                }
+            }
+            Value::Lambda { body, .. } => self.visit(body),
+            Value::Callsite { function, argument } => {
+                self.visit(function);
+                self.visit(argument);
+            }
+            Value::Guarded { alternatives, .. } => {
+                for (condition, body) in alternatives {
+                    self.visit(condition);
+                    self.visit(body);
+                }
+            }
+            Value::Tuple { dims } => {
+                for dim in dims {
+                    self.visit(dim);
+                }
+            }
+            Value::Let { bindings, body } => {
+                for (_, expr) in bindings {
+                    self.visit(expr);
+                }
+                self.visit(body);
+            }
+            _ => {}
+        }
+    }
+
+    /// Clauses whose guards may all fail do not cover the values they match, but can still be
+    /// unreachable because of earlier clauses.
+    fn check_match(&mut self, pattern_exprs: &[PatternExpr], location: Location) {
+        let mut rows: Vec<Vec<Pat>> = Vec::new();
+        for pattern_expr in pattern_exprs {
+            let row = vec![Pat::from(&pattern_expr.predicate)];
+            if !self.is_useful(&rows, &row) {
+                self.warnings.push(Warning {
+                    location: known_or(pattern_expr.predicate.location(), location),
+                    message: format!(
+                        "unreachable clause: {} is already matched",
+                        pattern_expr.predicate
+                    ),
+                });
+            }
+            if !may_fall_through(&pattern_expr.expr) {
+                rows.push(row);
+            }
+        }
+        if let Some(witness) = self.witness(&rows, 1) {
+            self.warnings.push(Warning {
+                location,
+                message: format!("non-exhaustive match: {} is not matched", witness[0]),
+            });
+        }
+    }
+
+    fn arity(&self, ctor: &Ctor) -> usize {
+        match ctor {
+            Ctor::Data(name) => self.ctors[name].1,
+            Ctor::Int(_) | Ctor::Str(_) | Ctor::Char(_) => 0,
+            Ctor::Tuple(arity) => *arity,
+        }
+    }
+
+    /// Whether some value matched by `row` is not matched by any of `rows`.
+    fn is_useful(&self, rows: &[Vec<Pat>], row: &[Pat]) -> bool {
+        let Some(head) = row.first() else {
+            return rows.is_empty();
+        };
+        match head {
+            Pat::Ctor(ctor, args) => {
+                let mut specialized = args.clone();
+                specialized.extend_from_slice(&row[1..]);
+                self.is_useful(&specialize(rows, ctor, args.len()), &specialized)
+            }
+            Pat::Wildcard => match self.complete_signature(rows) {
+                Some(ctors) => ctors.iter().any(|ctor| {
+                    let arity = self.arity(ctor);
+                    let mut specialized = vec![Pat::Wildcard; arity];
+                    specialized.extend_from_slice(&row[1..]);
+                    self.is_useful(&specialize(rows, ctor, arity), &specialized)
+                }),
+                None => self.is_useful(&default_rows(rows), &row[1..]),
+            },
+        }
+    }
+
+    /// Find `width` values, one per column, which no row matches.
+    fn witness(&self, rows: &[Vec<Pat>], width: usize) -> Option<Vec<Pat>> {
+        if width == 0 {
+            return rows.is_empty().then(Vec::new);
+        }
+        match self.complete_signature(rows) {
+            Some(ctors) => ctors.into_iter().find_map(|ctor| {
+                let arity = self.arity(&ctor);
+                let mut witness =
+                    self.witness(&specialize(rows, &ctor, arity), arity + width - 1)?;
+                let rest = witness.split_off(arity);
+                let mut result = vec![Pat::Ctor(ctor, witness)];
+                result.extend(rest);
+                Some(result)
+            }),
+            None => {
+                let rest = self.witness(&default_rows(rows), width - 1)?;
+                let mut result = vec![self.missing_ctor(rows)];
+                result.extend(rest);
+                Some(result)
+            }
+        }
+    }
+
+    /// Every constructor of the type in the first column, if the rows mention all of them.
+    fn complete_signature(&self, rows: &[Vec<Pat>]) -> Option<Vec<Ctor>> {
+        let heads = head_ctors(rows);
+        match heads.first()? {
+            Ctor::Data(name) => {
+                let ctors: Vec<Ctor> = self.types[&self.ctors[name].0]
+                    .iter()
+                    .map(|ctor| Ctor::Data(ctor.clone()))
+                    .collect();
+                ctors
+                    .iter()
+                    .all(|ctor| heads.contains(ctor))
+                    .then_some(ctors)
+            }
+            Ctor::Int(_) | Ctor::Str(_) | Ctor::Char(_) => None,
+            Ctor::Tuple(arity) => Some(vec![Ctor::Tuple(*arity)]),
+        }
+    }
+
+    /// An example of a value in the first column which none of the rows' constructors match.
+    fn missing_ctor(&self, rows: &[Vec<Pat>]) -> Pat {
+        let heads = head_ctors(rows);
+        match heads.first() {
+            None => Pat::Wildcard,
+            Some(Ctor::Data(name)) => {
+                let ctor = self.types[&self.ctors[name].0]
+                    .iter()
+                    .map(|ctor| Ctor::Data(ctor.clone()))
+                    .find(|ctor| !heads.contains(ctor))
+                    .expect("the signature is incomplete");
+                let arity = self.arity(&ctor);
+                Pat::Ctor(ctor, vec![Pat::Wildcard; arity])
+            }
+            Some(Ctor::Int(_)) => {
+                let value = (0..)
+                    .map(BigInt::from)
+                    .find(|value| !heads.contains(&Ctor::Int(value.clone())))
+                    .expect("finitely many ints are matched");
+                Pat::Ctor(Ctor::Int(value), Vec::new())
+            }
+            Some(Ctor::Str(_)) => {
+                let value = (0..)
+                    .map(|len| "a".repeat(len))
+                    .find(|value| !heads.contains(&Ctor::Str(value.clone())))
+                    .expect("finitely many strings are matched");
+                Pat::Ctor(Ctor::Str(value), Vec::new())
+            }
+            Some(Ctor::Char(_)) => {
+                let value = ('a'..=char::MAX)
+                    .find(|value| !heads.contains(&Ctor::Char(*value)))
+                    .expect("finitely many chars are matched");
+                Pat::Ctor(Ctor::Char(value), Vec::new())
+            }
+            Some(Ctor::Tuple(_)) => unreachable!("tuple signatures are always complete"),
+        }
+    }
+}
+
+/// Whether every guard of a clause may fail. A last guard of `otherwise` or `True` never does.
+fn may_fall_through(expr: &Value) -> bool {
+    match expr {
+        Value::Guarded { alternatives, .. } => !alternatives.iter().any(|(condition, _)| {
+            matches!(condition, Value::Id(id) if id.name() == "otherwise" || id.name() == "True")
+        }),
+        Value::Let { body, .. } => may_fall_through(body),
+        _ => false,
+    }
+}
+
+/// The distinct constructors in the first column, in order of appearance.
+fn head_ctors(rows: &[Vec<Pat>]) -> Vec<Ctor> {
+    let mut heads = Vec::new();
+    for row in rows {
+        if let Pat::Ctor(ctor, _) = &row[0] {
+            if !heads.contains(ctor) {
+                heads.push(ctor.clone());
+            }
+        }
+    }
+    heads
+}
+
+/// The rows which match `ctor` in the first column, with its arguments in place of it.
+fn specialize(rows: &[Vec<Pat>], ctor: &Ctor, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let mut specialized = match &row[0] {
+                Pat::Wildcard => vec![Pat::Wildcard; arity],
+                Pat::Ctor(head, args) if head == ctor => args.clone(),
+                Pat::Ctor(..) => return None,
+            };
+            specialized.extend_from_slice(&row[1..]);
+            Some(specialized)
+        })
+        .collect()
+}
+
+/// The rows which match anything in the first column, without it.
+fn default_rows(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter(|row| matches!(row[0], Pat::Wildcard))
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
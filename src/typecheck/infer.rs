@@ -0,0 +1,1205 @@
+use std::collections::HashMap;
+
+use crate::{
+    decl::{build_lambdas, Binding},
+    env::Env,
+    id::{derived_id, gensym, Id},
+    location::Location,
+    typecheck::{
+        classes::{
+            default_method_name, dictionary_binding, dictionary_name, instance_method_name,
+            selector_bindings, superclass_name,
+        },
+        deps::binding_groups,
+        error::TypeError,
+        types::{skolem_name, Scheme, Type, TypePrinter, TypeVar, FUNCTION},
+    },
+    value::{ClassDecl, Constraint, DataDecl, InstanceDecl, Predicate, Signature, TypeExpr, Value},
+};
+
+pub(crate) type TypeEnv = rpds::RedBlackTreeMap<String, Scheme>;
+
+/// The dictionaries which each placeholder argument left by inference stands for, by the name of
+/// the placeholder.
+pub(crate) type Dictionaries = HashMap<String, Vec<Value>>;
+
+/// Infer the type of every binding in a program, rejecting the program if any of them is
+/// ill-typed or does not agree with its signature. Builtins are typed by their signatures, and
+/// constructors by their `data` declarations.
+///
+/// Each use of an overloaded binding is applied to a placeholder for the dictionaries of its
+/// constraints, and each overloaded binding is wrapped in a lambda for each dictionary it takes.
+/// The bindings which build the dictionaries of instances and select their fields are returned
+/// along with what each placeholder stands for, so that they can be elaborated.
+pub(crate) fn check_program(
+    env: &Env,
+    data_decls: &[DataDecl],
+    class_decls: &[ClassDecl],
+    instance_decls: &[InstanceDecl],
+    bindings: &mut [Binding],
+    signatures: &[Signature],
+) -> Result<(Vec<Binding>, Dictionaries), TypeError> {
+    let mut checker = Checker::new();
+    // Register every type name first, since data declarations may refer to each other.
+    for data_decl in data_decls {
+        let name = &data_decl.name;
+        // Data types are already unique, so only a builtin type can be redefined.
+        if checker
+            .type_arities
+            .insert(name.name().to_string(), data_decl.params.len())
+            .is_some()
+        {
+            return Err(TypeError::BuiltinType(name.clone()));
+        }
+    }
+    for class_decl in class_decls {
+        let superclasses = class_decl
+            .superclasses
+            .iter()
+            .map(|constraint| constraint.class.name().to_string())
+            .collect();
+        checker
+            .classes
+            .insert(class_decl.name.name().to_string(), superclasses);
+    }
+    for instance in instance_decls {
+        checker.add_instance(instance)?;
+    }
+    let mut type_env = TypeEnv::new();
+    for builtin in env.builtins() {
+        let scheme = checker.signature_scheme(&builtin.signature)?;
+        type_env.insert_mut(builtin.name.clone(), scheme);
+    }
+    for data_decl in data_decls {
+        for ctor in &data_decl.ctors {
+            let scheme = checker.ctor_scheme(data_decl, &data_decl.ctor_signature(ctor))?;
+            checker.ctors.insert(
+                ctor.name.name().to_string(),
+                (ctor.fields.len(), scheme.clone()),
+            );
+            type_env.insert_mut(ctor.name.name().to_string(), scheme);
+        }
+    }
+    let locations: HashMap<&str, Location> = bindings
+        .iter()
+        .map(|(name, _)| (name.name(), name.location()))
+        .collect();
+    let mut declared = HashMap::new();
+    for class_decl in class_decls {
+        let class = class_decl.name.name();
+        for method in &class_decl.methods {
+            let scheme = checker.method_scheme(class_decl, method, None)?;
+            let default = default_method_name(class, method.name.name());
+            if let Some(&location) = locations.get(default.as_str()) {
+                declared.insert(
+                    default,
+                    Declared {
+                        scheme: scheme.clone(),
+                        location,
+                    },
+                );
+            }
+            type_env.insert_mut(method.name.name().to_string(), scheme);
+        }
+    }
+    for instance in instance_decls {
+        let class_decl = class_decls
+            .iter()
+            .find(|class_decl| class_decl.name.name() == instance.class.name())
+            .expect("instances are of known classes");
+        for method in &class_decl.methods {
+            let name = instance_method_name(
+                instance.class.name(),
+                &instance.type_name,
+                method.name.name(),
+            );
+            if let Some(&location) = locations.get(name.as_str()) {
+                let scheme = checker.method_scheme(class_decl, method, Some(instance))?;
+                declared.insert(name, Declared { scheme, location });
+            }
+        }
+    }
+    for signature in signatures {
+        let scheme = checker.declared_scheme(signature)?;
+        declared.insert(
+            signature.name.name().to_string(),
+            Declared {
+                scheme,
+                location: signature.name.location(),
+            },
+        );
+    }
+    let type_env = checker.infer_bindings(&type_env, bindings, &declared)?;
+    if let Some(wanted) = checker.wanted.first() {
+        return Err(TypeError::Ambiguous {
+            class: wanted.class.clone(),
+            location: wanted.location,
+        });
+    }
+    if let Some((main, _)) = bindings.iter().find(|(name, _)| name.name() == "main") {
+        // The entrypoint applies `main` to `()`, and has no dictionaries to pass it.
+        let location = main.location();
+        if let Some((class, _)) = type_env["main"].constraints.first() {
+            return Err(TypeError::Ambiguous {
+                class: class.clone(),
+                location,
+            });
+        }
+        let (main_type, _) = checker.instantiate(&type_env["main"], location);
+        let expected = Type::function(Type::tuple(Vec::new(), location), checker.fresh(), location);
+        checker.unify(&expected, &main_type, location)?;
+    }
+    let mut class_bindings = Vec::new();
+    for class_decl in class_decls {
+        class_bindings.extend(selector_bindings(class_decl));
+    }
+    for instance in instance_decls {
+        let class_decl = class_decls
+            .iter()
+            .find(|class_decl| class_decl.name.name() == instance.class.name())
+            .expect("instances are of known classes");
+        let (context, superclasses) = checker.instance_superclasses(class_decl, instance)?;
+        class_bindings.push(dictionary_binding(
+            class_decl,
+            instance,
+            context,
+            superclasses,
+        ));
+    }
+    Ok((class_bindings, checker.dictionaries))
+}
+
+/// The type scheme a binding was declared with, and the location of its signature.
+struct Declared {
+    scheme: Scheme,
+    location: Location,
+}
+
+/// An instance, as the solver sees it.
+struct Instance {
+    /// The number of type variables the type constructor is applied to.
+    params: usize,
+    /// The constraints on those type variables, by their position, which the instance's
+    /// dictionary takes dictionaries for.
+    context: Vec<(String, usize)>,
+    dictionary: String,
+}
+
+/// A constraint which an expression requires, along with the placeholder which stands for its
+/// dictionary until the constraint is solved.
+struct Wanted {
+    class: String,
+    ty: Type,
+    placeholder: Id,
+    location: Location,
+}
+
+/// A dictionary which is in scope for a rigid type, such as a dictionary parameter of a binding
+/// whose signature has a context.
+struct Given {
+    class: String,
+    skolem: String,
+    dictionary: Value,
+}
+
+/// Hindley–Milner inference. Type variables are solved in place by unification, and each
+/// remembers the let-nesting level it was introduced at so that generalization only quantifies
+/// variables which are not free in the enclosing env.
+struct Checker {
+    /// The solution for each type variable, if it has been unified with something.
+    substitution: Vec<Option<Type>>,
+    levels: Vec<usize>,
+    level: usize,
+    /// Type constructors in scope, with their arities.
+    type_arities: HashMap<String, usize>,
+    /// Data constructors, with their arities and types.
+    ctors: HashMap<String, (usize, Scheme)>,
+    /// Classes, with their superclasses in the order of their dictionaries.
+    classes: HashMap<String, Vec<String>>,
+    /// Instances, by class and type constructor.
+    instances: HashMap<(String, String), Instance>,
+    /// The constraints required by the expressions inferred so far, which the enclosing binding
+    /// group has yet to solve.
+    wanted: Vec<Wanted>,
+    /// The monomorphic type variable of each binding of the group being inferred which has no
+    /// declared type, with its name and the placeholder for the dictionaries the group takes.
+    recursive: HashMap<TypeVar, (String, Id)>,
+    dictionaries: Dictionaries,
+}
+
+impl Checker {
+    fn new() -> Self {
+        Self {
+            substitution: Vec::new(),
+            levels: Vec::new(),
+            level: 0,
+            type_arities: HashMap::from([
+                ("Int".to_string(), 0),
+                ("Float".to_string(), 0),
+                ("String".to_string(), 0),
+                ("Char".to_string(), 0),
+            ]),
+            ctors: HashMap::new(),
+            classes: HashMap::new(),
+            instances: HashMap::new(),
+            wanted: Vec::new(),
+            recursive: HashMap::new(),
+            dictionaries: HashMap::new(),
+        }
+    }
+
+    fn new_var(&mut self) -> TypeVar {
+        self.substitution.push(None);
+        self.levels.push(self.level);
+        self.substitution.len() - 1
+    }
+
+    fn fresh(&mut self) -> Type {
+        Type::Var(self.new_var())
+    }
+
+    /// Follow solved type variables until reaching a constructor or an unsolved variable. An
+    /// application whose function is solved with a constructor becomes that constructor.
+    fn resolve(&self, ty: &Type) -> Type {
+        let mut ty = ty;
+        while let Type::Var(var) = ty {
+            match &self.substitution[*var] {
+                Some(solution) => ty = solution,
+                None => break,
+            }
+        }
+        match ty {
+            Type::App(function, arg) => Type::app(self.resolve(function), (**arg).clone()),
+            ty => ty.clone(),
+        }
+    }
+
+    /// Resolve `ty` completely, including its arguments.
+    fn zonk(&self, ty: &Type) -> Type {
+        match self.resolve(ty) {
+            Type::Con { name, args, origin } => Type::Con {
+                name,
+                args: args.iter().map(|arg| self.zonk(arg)).collect(),
+                origin,
+            },
+            Type::App(function, arg) => Type::app(self.zonk(&function), self.zonk(&arg)),
+            var => var,
+        }
+    }
+
+    /// Instantiate `scheme` with fresh type variables, along with the types its constraints
+    /// apply to. The result originates at `site`, so that conflicts point at the use of a binding
+    /// rather than at its definition.
+    fn instantiate(&mut self, scheme: &Scheme, site: Location) -> (Type, Vec<(String, Type)>) {
+        let fresh: HashMap<TypeVar, Type> =
+            scheme.vars.iter().map(|&var| (var, self.fresh())).collect();
+        let constraints = scheme
+            .constraints
+            .iter()
+            .map(|(class, var)| (class.clone(), fresh[var].clone()))
+            .collect();
+        (self.substitute(&scheme.ty, &fresh, site), constraints)
+    }
+
+    fn substitute(&self, ty: &Type, fresh: &HashMap<TypeVar, Type>, site: Location) -> Type {
+        match self.resolve(ty) {
+            Type::Var(var) => fresh.get(&var).cloned().unwrap_or(Type::Var(var)),
+            Type::Con { name, args, .. } => Type::Con {
+                name,
+                args: args
+                    .iter()
+                    .map(|arg| self.substitute(arg, fresh, site))
+                    .collect(),
+                origin: site,
+            },
+            Type::App(function, arg) => Type::app(
+                self.substitute(&function, fresh, site),
+                self.substitute(&arg, fresh, site),
+            ),
+        }
+    }
+
+    /// Quantify the variables of `ty` which were introduced inside the current level, along with
+    /// the `constraints` on them.
+    fn generalize(&self, ty: &Type, constraints: &[(String, TypeVar, Id)]) -> Scheme {
+        let ty = self.zonk(ty);
+        let mut vars = Vec::new();
+        self.collect_generic_vars(&ty, &mut vars);
+        for (_, var, _) in constraints {
+            if !vars.contains(var) {
+                vars.push(*var);
+            }
+        }
+        Scheme {
+            vars,
+            constraints: constraints
+                .iter()
+                .map(|(class, var, _)| (class.clone(), *var))
+                .collect(),
+            ty,
+        }
+    }
+
+    fn collect_generic_vars(&self, ty: &Type, vars: &mut Vec<TypeVar>) {
+        match ty {
+            Type::Var(var) => {
+                if self.levels[*var] > self.level && !vars.contains(var) {
+                    vars.push(*var);
+                }
+            }
+            Type::Con { args, .. } => {
+                for arg in args {
+                    self.collect_generic_vars(arg, vars);
+                }
+            }
+            Type::App(function, arg) => {
+                self.collect_generic_vars(function, vars);
+                self.collect_generic_vars(arg, vars);
+            }
+        }
+    }
+
+    fn unify(&mut self, expected: &Type, found: &Type, site: Location) -> Result<(), TypeError> {
+        match (self.resolve(expected), self.resolve(found)) {
+            (Type::Var(a), Type::Var(b)) if a == b => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => self.bind(var, ty, site),
+            (
+                Type::Con {
+                    name: expected_name,
+                    args: expected_args,
+                    ..
+                },
+                Type::Con {
+                    name: found_name,
+                    args: found_args,
+                    ..
+                },
+            ) if expected_name == found_name && expected_args.len() == found_args.len() => {
+                for (expected, found) in expected_args.iter().zip(&found_args) {
+                    self.unify(expected, found, site)?;
+                }
+                Ok(())
+            }
+            (Type::App(expected_function, expected_arg), Type::App(found_function, found_arg)) => {
+                self.unify(&expected_function, &found_function, site)?;
+                self.unify(&expected_arg, &found_arg, site)
+            }
+            // `m a` is `List Int` when `m` is `List` and `a` is `Int`.
+            (
+                Type::App(function, arg),
+                Type::Con {
+                    name,
+                    mut args,
+                    origin,
+                },
+            ) if !args.is_empty() => {
+                let last = args.pop().expect("the constructor has arguments");
+                self.unify(&function, &Type::Con { name, args, origin }, site)?;
+                self.unify(&arg, &last, site)
+            }
+            (
+                Type::Con {
+                    name,
+                    mut args,
+                    origin,
+                },
+                Type::App(function, arg),
+            ) if !args.is_empty() => {
+                let last = args.pop().expect("the constructor has arguments");
+                self.unify(&Type::Con { name, args, origin }, &function, site)?;
+                self.unify(&last, &arg, site)
+            }
+            (expected_type, found_type) => {
+                let mut printer = TypePrinter::default();
+                Err(TypeError::Mismatch {
+                    expected: printer.print(&self.zonk(expected)),
+                    expected_at: known_or(origin(&expected_type), site),
+                    found: printer.print(&self.zonk(found)),
+                    found_at: known_or(origin(&found_type), site),
+                })
+            }
+        }
+    }
+
+    fn bind(&mut self, var: TypeVar, ty: Type, site: Location) -> Result<(), TypeError> {
+        if self.occurs(var, &ty) {
+            let mut printer = TypePrinter::default();
+            return Err(TypeError::InfiniteType {
+                var: printer.print(&Type::Var(var)),
+                ty: printer.print(&self.zonk(&ty)),
+                location: site,
+            });
+        }
+        self.substitution[var] = Some(ty);
+        Ok(())
+    }
+
+    /// Check whether `var` occurs in `ty`, lowering the levels of the variables in `ty` to that
+    /// of `var` along the way, since they are about to become reachable from it.
+    fn occurs(&mut self, var: TypeVar, ty: &Type) -> bool {
+        match self.resolve(ty) {
+            Type::Var(other) => {
+                self.levels[other] = self.levels[other].min(self.levels[var]);
+                other == var
+            }
+            Type::Con { args, .. } => args.iter().any(|arg| self.occurs(var, arg)),
+            Type::App(function, arg) => self.occurs(var, &function) || self.occurs(var, &arg),
+        }
+    }
+
+    /// Infer each strongly connected group of bindings in dependency order, generalizing each
+    /// group before moving on to the groups which use it. Bindings with a declared type are
+    /// known by that type everywhere, including in their own definition, and are checked against
+    /// it once their definition has been inferred.
+    ///
+    /// The constraints which a group requires are solved once it has been inferred. Those left
+    /// on its quantified type variables become dictionary parameters, which every binding of the
+    /// group takes, and which its recursive uses pass along.
+    fn infer_bindings(
+        &mut self,
+        env: &TypeEnv,
+        bindings: &mut [Binding],
+        declared: &HashMap<String, Declared>,
+    ) -> Result<TypeEnv, TypeError> {
+        let mut env = env.clone();
+        for (name, _) in bindings.iter() {
+            if let Some(declared) = declared.get(name.name()) {
+                env.insert_mut(name.name().to_string(), declared.scheme.clone());
+            }
+        }
+        for group in binding_groups(bindings, |name| declared.contains_key(name)) {
+            self.level += 1;
+            let outer_wanted = std::mem::take(&mut self.wanted);
+            let placeholder = gensym(bindings[group[0]].0.location());
+            let mut group_env = env.clone();
+            let vars: Vec<TypeVar> = group
+                .iter()
+                .map(|&i| {
+                    let var = self.new_var();
+                    let name = bindings[i].0.name();
+                    if !declared.contains_key(name) {
+                        group_env.insert_mut(name.to_string(), Scheme::mono(Type::Var(var)));
+                        self.recursive
+                            .insert(var, (name.to_string(), placeholder.clone()));
+                    }
+                    var
+                })
+                .collect();
+            for (&i, &var) in group.iter().zip(&vars) {
+                let (name, expr) = &mut bindings[i];
+                let ty = self.infer(&group_env, expr, name.location())?;
+                self.unify(&Type::Var(var), &ty, name.location())?;
+                if let Some(declared) = declared.get(name.name()) {
+                    self.check_signature(name, &ty, declared)?;
+                }
+            }
+            self.level -= 1;
+            let wanted = std::mem::replace(&mut self.wanted, outer_wanted);
+            for var in &vars {
+                self.recursive.remove(var);
+            }
+            if let [i] = group[..] {
+                if let Some(declared) = declared.get(bindings[i].0.name()) {
+                    // Declared bindings are alone in their group, and take the dictionaries
+                    // their signature calls for.
+                    let mut givens = Vec::new();
+                    let mut params = Vec::new();
+                    for (class, var) in &declared.scheme.constraints {
+                        let param = gensym(declared.location);
+                        self.add_given(
+                            class,
+                            &skolem_name(*var),
+                            Value::Id(param.clone()),
+                            &mut givens,
+                        );
+                        params.push(param);
+                    }
+                    self.solve(wanted, &givens, false)?;
+                    let expr = &mut bindings[i].1;
+                    *expr = build_lambdas(params, std::mem::replace(expr, Value::Null));
+                    continue;
+                }
+            }
+            let constraints = self.solve(wanted, &[], true)?;
+            for (&i, &var) in group.iter().zip(&vars) {
+                let ty = self.zonk(&Type::Var(var));
+                let mut free = Vec::new();
+                self.collect_generic_vars(&ty, &mut free);
+                if let Some((class, _, param)) =
+                    constraints.iter().find(|(_, var, _)| !free.contains(var))
+                {
+                    return Err(TypeError::Ambiguous {
+                        class: class.clone(),
+                        location: param.location(),
+                    });
+                }
+                let (name, expr) = &mut bindings[i];
+                let params = constraints
+                    .iter()
+                    .map(|(.., param)| param.clone())
+                    .collect();
+                *expr = build_lambdas(params, std::mem::replace(expr, Value::Null));
+                env.insert_mut(name.name().to_string(), self.generalize(&ty, &constraints));
+            }
+            self.dictionaries.insert(
+                placeholder.name().to_string(),
+                constraints
+                    .iter()
+                    .map(|(.., param)| Value::Id(param.clone()))
+                    .collect(),
+            );
+        }
+        Ok(env)
+    }
+
+    /// Solve the constraints a binding group requires, once the group has been inferred. A
+    /// constraint on a type constructor is solved by an instance of it, whose own context may
+    /// require further constraints, and one on a rigid type by one of `givens`. A constraint on
+    /// a variable which the group quantifies becomes a dictionary parameter of the group, which
+    /// is returned, unless the group may not be generalized, since its type is declared. Any
+    /// other constraint is on a variable of the enclosing env, and is left to the enclosing
+    /// group.
+    fn solve(
+        &mut self,
+        mut wanted: Vec<Wanted>,
+        givens: &[Given],
+        generalize: bool,
+    ) -> Result<Vec<(String, TypeVar, Id)>, TypeError> {
+        let mut params: Vec<(String, TypeVar, Id)> = Vec::new();
+        while let Some(Wanted {
+            class,
+            ty,
+            placeholder,
+            location,
+        }) = wanted.pop()
+        {
+            let ty = self.zonk(&ty);
+            let dictionary = match &ty {
+                Type::Var(var) if self.levels[*var] <= self.level => {
+                    self.wanted.push(Wanted {
+                        class,
+                        ty,
+                        placeholder,
+                        location,
+                    });
+                    continue;
+                }
+                Type::Var(_) if !generalize => {
+                    return Err(TypeError::Ambiguous { class, location });
+                }
+                &Type::Var(var) => {
+                    match params
+                        .iter()
+                        .find(|(other, v, _)| *other == class && *v == var)
+                    {
+                        Some((.., param)) => Value::Id(param.clone()),
+                        None => {
+                            let param = gensym(location);
+                            params.push((class, var, param.clone()));
+                            Value::Id(param)
+                        }
+                    }
+                }
+                Type::Con { name, args, .. } => {
+                    let given = givens.iter().find(|given| {
+                        given.class == class && args.is_empty() && given.skolem == *name
+                    });
+                    let instance = self.instances.get(&(class.clone(), name.clone()));
+                    match (given, instance) {
+                        (Some(given), _) => given.dictionary.clone(),
+                        (None, Some(instance)) if instance.params == args.len() => {
+                            let mut dictionary =
+                                Value::Id(derived_id(&instance.dictionary, location));
+                            for (class, param) in &instance.context {
+                                let placeholder = gensym(location);
+                                dictionary = Value::Callsite {
+                                    function: Box::new(dictionary),
+                                    argument: Box::new(Value::Id(placeholder.clone())),
+                                };
+                                wanted.push(Wanted {
+                                    class: class.clone(),
+                                    ty: args[*param].clone(),
+                                    placeholder,
+                                    location,
+                                });
+                            }
+                            dictionary
+                        }
+                        _ => return Err(self.no_instance(class, &ty, location)),
+                    }
+                }
+                Type::App(..) => return Err(self.no_instance(class, &ty, location)),
+            };
+            self.dictionaries
+                .insert(placeholder.name().to_string(), vec![dictionary]);
+        }
+        Ok(params)
+    }
+
+    fn no_instance(&self, class: String, ty: &Type, location: Location) -> TypeError {
+        TypeError::NoInstance {
+            class,
+            ty: TypePrinter::default().print_arg(ty),
+            location,
+        }
+    }
+
+    /// Add the dictionary of `class` for the rigid type `skolem` to `givens`, along with the
+    /// dictionaries of its superclasses, which it contains.
+    fn add_given(&self, class: &str, skolem: &str, dictionary: Value, givens: &mut Vec<Given>) {
+        for superclass in &self.classes[class] {
+            let selector = derived_id(&superclass_name(class, superclass), dictionary.location());
+            let dictionary = Value::Callsite {
+                function: Box::new(Value::Id(selector)),
+                argument: Box::new(dictionary.clone()),
+            };
+            self.add_given(superclass, skolem, dictionary, givens);
+        }
+        givens.push(Given {
+            class: class.to_string(),
+            skolem: skolem.to_string(),
+            dictionary,
+        });
+    }
+
+    /// Solve the superclass constraints of `instance`, whose type is held rigid. Returns the
+    /// parameters which its dictionary takes for its context, and the dictionaries of its
+    /// superclasses in terms of them.
+    fn instance_superclasses(
+        &mut self,
+        class_decl: &ClassDecl,
+        instance: &InstanceDecl,
+    ) -> Result<(Vec<Id>, Vec<Value>), TypeError> {
+        let location = instance.class.location();
+        let skolems: Vec<String> = instance
+            .params
+            .iter()
+            .map(|_| skolem_name(self.new_var()))
+            .collect();
+        let mut givens = Vec::new();
+        let mut params = Vec::new();
+        for constraint in &instance.context {
+            let param = gensym(location);
+            let index = instance_param(instance, constraint);
+            self.add_given(
+                constraint.class.name(),
+                &skolems[index],
+                Value::Id(param.clone()),
+                &mut givens,
+            );
+            params.push(param);
+        }
+        let ty = Type::con(
+            &instance.type_name,
+            skolems
+                .iter()
+                .map(|skolem| Type::con(skolem, Vec::new(), location))
+                .collect(),
+            location,
+        );
+        let wanted: Vec<Wanted> = class_decl
+            .superclasses
+            .iter()
+            .map(|superclass| Wanted {
+                class: superclass.class.name().to_string(),
+                ty: ty.clone(),
+                placeholder: gensym(location),
+                location,
+            })
+            .collect();
+        let placeholders: Vec<String> = wanted
+            .iter()
+            .map(|wanted| wanted.placeholder.name().to_string())
+            .collect();
+        self.solve(wanted, &givens, false)?;
+        let superclasses = placeholders
+            .iter()
+            .map(|placeholder| self.dictionaries[placeholder][0].clone())
+            .collect();
+        Ok((params, superclasses))
+    }
+
+    /// Check that the declared type of `name` is an instance of its inferred type `ty`, by
+    /// unifying `ty` with the declared type with its variables held rigid.
+    fn check_signature(
+        &mut self,
+        name: &Id,
+        ty: &Type,
+        declared: &Declared,
+    ) -> Result<(), TypeError> {
+        let inferred = TypePrinter::default().print(&self.zonk(ty));
+        let rigid = declared
+            .scheme
+            .vars
+            .iter()
+            .map(|&var| {
+                let skolem = Type::con(&skolem_name(var), Vec::new(), declared.location);
+                (var, skolem)
+            })
+            .collect();
+        let rigid = self.substitute(&declared.scheme.ty, &rigid, declared.location);
+        if self.unify(&rigid, ty, declared.location).is_err() {
+            return Err(TypeError::SignatureMismatch {
+                name: name.clone(),
+                declared: TypePrinter::default().print(&declared.scheme.ty),
+                inferred,
+                location: declared.location,
+            });
+        }
+        Ok(())
+    }
+
+    /// Infer the type of `expr`. `site` is the nearest enclosing source location, for
+    /// expressions which have none of their own.
+    ///
+    /// Each use of an overloaded binding is applied to a placeholder for each of its
+    /// constraints, which are left to the enclosing binding group to solve.
+    fn infer(
+        &mut self,
+        env: &TypeEnv,
+        expr: &mut Value,
+        site: Location,
+    ) -> Result<Type, TypeError> {
+        let site = known_or(expr.location(), site);
+        match expr {
+            Value::Literal { value, .. } => self.infer(env, value, site),
+            Value::Int(_) | Value::BigInt(_) => Ok(Type::int(site)),
+            Value::Float(_) => Ok(Type::float(site)),
+            Value::Str(_) => Ok(Type::string(site)),
+            Value::Char(_) => Ok(Type::char(site)),
+            // An error at runtime, which may stand for a value of any type.
+            Value::NoField { .. } => Ok(self.fresh()),
+            Value::Id(id) => {
+                let Some(scheme) = env.get(id.name()).cloned() else {
+                    return Err(TypeError::UnresolvedSymbol(id.clone()));
+                };
+                let name = id.name().to_string();
+                let (ty, constraints) = self.instantiate(&scheme, site);
+                let mut placeholders = Vec::new();
+                for (class, ty) in constraints {
+                    let placeholder = gensym(site);
+                    placeholders.push(placeholder.clone());
+                    self.wanted.push(Wanted {
+                        class,
+                        ty,
+                        placeholder,
+                        location: site,
+                    });
+                }
+                if let Type::Var(var) = scheme.ty {
+                    match self.recursive.get(&var) {
+                        Some((member, placeholder)) if *member == name => {
+                            placeholders.push(placeholder.clone())
+                        }
+                        _ => {}
+                    }
+                }
+                for placeholder in placeholders {
+                    *expr = Value::Callsite {
+                        function: Box::new(std::mem::replace(expr, Value::Null)),
+                        argument: Box::new(Value::Id(placeholder)),
+                    };
+                }
+                Ok(ty)
+            }
+            Value::Lambda { param, body } => {
+                let domain = self.fresh();
+                let body_env = env.insert(param.name().to_string(), Scheme::mono(domain.clone()));
+                let range = self.infer(&body_env, body, site)?;
+                Ok(Type::function(domain, range, site))
+            }
+            Value::Callsite { function, argument } => {
+                let function_type = self.infer(env, function, site)?;
+                if let Type::Con { name, origin, .. } = self.resolve(&function_type) {
+                    if name != FUNCTION {
+                        return Err(TypeError::NotAFunction {
+                            ty: TypePrinter::default().print(&self.zonk(&function_type)),
+                            ty_at: known_or(origin, site),
+                            location: site,
+                        });
+                    }
+                }
+                let argument_site = known_or(argument.location(), site);
+                let argument_type = self.infer(env, argument, argument_site)?;
+                let range = self.fresh();
+                self.unify(
+                    &function_type,
+                    &Type::function(argument_type, range.clone(), argument_site),
+                    argument_site,
+                )?;
+                Ok(range)
+            }
+            Value::Tuple { dims } => {
+                let dims = dims
+                    .iter_mut()
+                    .map(|dim| self.infer(env, dim, site))
+                    .collect::<Result<_, _>>()?;
+                Ok(Type::tuple(dims, site))
+            }
+            Value::Match {
+                subject,
+                pattern_exprs,
+                location,
+            } => {
+                let subject_type = self.infer(env, subject, *location)?;
+                let result = self.fresh();
+                for pattern_expr in pattern_exprs {
+                    let arm_env =
+                        self.infer_predicate(env, &pattern_expr.predicate, &subject_type, site)?;
+                    let arm_site = known_or(pattern_expr.expr.location(), site);
+                    let ty = self.infer(&arm_env, &mut pattern_expr.expr, arm_site)?;
+                    self.unify(&result, &ty, arm_site)?;
+                }
+                Ok(result)
+            }
+            Value::Let { bindings, body } => {
+                let env = self.infer_bindings(env, bindings, &HashMap::new())?;
+                self.infer(&env, body, site)
+            }
+            Value::Guarded { alternatives, .. } => {
+                let result = self.fresh();
+                for (condition, body) in alternatives {
+                    let condition_site = known_or(condition.location(), site);
+                    let ty = self.infer(env, condition, condition_site)?;
+                    self.unify(
+                        &Type::con("Bool", Vec::new(), condition_site),
+                        &ty,
+                        condition_site,
+                    )?;
+                    let body_site = known_or(body.location(), site);
+                    let ty = self.infer(env, body, body_site)?;
+                    self.unify(&result, &ty, body_site)?;
+                }
+                Ok(result)
+            }
+            Value::Null
+            | Value::Closure { .. }
+            | Value::Thunk(_)
+            | Value::Builtin(_)
+            | Value::PartialApp { .. }
+            | Value::Ctor { .. } => unreachable!("{expr:?} only exists at runtime"),
+            Value::Infix { .. } | Value::RecordUpdate { .. } | Value::FieldAccess { .. } => {
+                unreachable!("{expr:?} is resolved before type checking")
+            }
+        }
+    }
+
+    /// Check `predicate` against a subject of type `expected`, returning `env` extended with the
+    /// variables it binds.
+    fn infer_predicate(
+        &mut self,
+        env: &TypeEnv,
+        predicate: &Predicate,
+        expected: &Type,
+        site: Location,
+    ) -> Result<TypeEnv, TypeError> {
+        match predicate {
+            Predicate::Irrefutable(id) => {
+                Ok(env.insert(id.name().to_string(), Scheme::mono(expected.clone())))
+            }
+            Predicate::Wildcard(_) => Ok(env.clone()),
+            Predicate::Int(_, location) | Predicate::BigInt(_, location) => {
+                self.unify(expected, &Type::int(*location), *location)?;
+                Ok(env.clone())
+            }
+            Predicate::Str(_, location) => {
+                self.unify(expected, &Type::string(*location), *location)?;
+                Ok(env.clone())
+            }
+            Predicate::Char(_, location) => {
+                self.unify(expected, &Type::char(*location), *location)?;
+                Ok(env.clone())
+            }
+            Predicate::As(id, predicate) => {
+                let env = self.infer_predicate(env, predicate, expected, site)?;
+                Ok(env.insert(id.name().to_string(), Scheme::mono(expected.clone())))
+            }
+            Predicate::Lazy(predicate, _) => self.infer_predicate(env, predicate, expected, site),
+            Predicate::Record(..) => {
+                unreachable!("{predicate:?} is resolved before type checking")
+            }
+            Predicate::Tuple(predicates, location) => {
+                let site = known_or(*location, site);
+                let dims: Vec<Type> = predicates.iter().map(|_| self.fresh()).collect();
+                self.unify(expected, &Type::tuple(dims.clone(), site), site)?;
+                let mut env = env.clone();
+                for (predicate, dim) in predicates.iter().zip(&dims) {
+                    env = self.infer_predicate(&env, predicate, dim, site)?;
+                }
+                Ok(env)
+            }
+            Predicate::Ctor(ctor, predicates) => {
+                let site = known_or(ctor.location(), site);
+                let Some((arity, scheme)) = self.ctors.get(ctor.name()).cloned() else {
+                    return Err(TypeError::UnresolvedSymbol(ctor.clone()));
+                };
+                if arity != predicates.len() {
+                    return Err(TypeError::CtorArity {
+                        ctor: ctor.clone(),
+                        expected: arity,
+                        found: predicates.len(),
+                    });
+                }
+                let mut fields = Vec::new();
+                let (mut ty, _) = self.instantiate(&scheme, site);
+                while let Type::Con { name, mut args, .. } = ty {
+                    if name != FUNCTION {
+                        ty = Type::Con {
+                            name,
+                            args,
+                            origin: site,
+                        };
+                        break;
+                    }
+                    ty = args.pop().expect("functions have a range");
+                    fields.push(args.pop().expect("functions have a domain"));
+                }
+                self.unify(expected, &ty, site)?;
+                let mut env = env.clone();
+                for (predicate, field) in predicates.iter().zip(&fields) {
+                    env = self.infer_predicate(&env, predicate, field, site)?;
+                }
+                Ok(env)
+            }
+        }
+    }
+
+    /// Convert a builtin's signature into a type scheme which quantifies all of its variables.
+    fn signature_scheme(&mut self, signature: &TypeExpr) -> Result<Scheme, TypeError> {
+        let mut vars = HashMap::new();
+        let ty = self.type_from_expr(signature, &mut vars, true)?;
+        Ok(Scheme {
+            vars: vars.into_values().collect(),
+            constraints: Vec::new(),
+            ty,
+        })
+    }
+
+    /// Convert a type signature into a type scheme, whose context may only constrain the
+    /// variables of its type.
+    fn declared_scheme(&mut self, signature: &Signature) -> Result<Scheme, TypeError> {
+        let mut vars = HashMap::new();
+        let ty = self.type_from_expr(&signature.type_expr, &mut vars, true)?;
+        Ok(Scheme {
+            constraints: self.context_constraints(&signature.context, &vars)?,
+            vars: vars.into_values().collect(),
+            ty,
+        })
+    }
+
+    /// The type scheme of a method of a class, whose first constraint is on the variable of the
+    /// class. For the definition of the method in an instance, the variable is replaced with the
+    /// type of the instance, and the first constraints are those of the instance's context.
+    fn method_scheme(
+        &mut self,
+        class_decl: &ClassDecl,
+        method: &Signature,
+        instance: Option<&InstanceDecl>,
+    ) -> Result<Scheme, TypeError> {
+        let class_var = self.new_var();
+        let mut vars = HashMap::from([(class_decl.var.name().to_string(), class_var)]);
+        let ty = self.type_from_expr(&method.type_expr, &mut vars, true)?;
+        let own_constraints = self.context_constraints(&method.context, &vars)?;
+        vars.remove(class_decl.var.name());
+        let mut vars: Vec<TypeVar> = vars.into_values().collect();
+        let (ty, mut constraints) = match instance {
+            None => {
+                vars.push(class_var);
+                let constraint = (class_decl.name.name().to_string(), class_var);
+                (ty, vec![constraint])
+            }
+            Some(instance) => {
+                let location = instance.class.location();
+                let mut instance_vars = HashMap::new();
+                let params = instance
+                    .params
+                    .iter()
+                    .map(|param| {
+                        let var = self.new_var();
+                        instance_vars.insert(param.name().to_string(), var);
+                        vars.push(var);
+                        Type::Var(var)
+                    })
+                    .collect();
+                let head = Type::con(&instance.type_name, params, location);
+                let ty = self.substitute(&ty, &HashMap::from([(class_var, head)]), location);
+                (
+                    ty,
+                    self.context_constraints(&instance.context, &instance_vars)?,
+                )
+            }
+        };
+        constraints.extend(own_constraints);
+        Ok(Scheme {
+            vars,
+            constraints,
+            ty,
+        })
+    }
+
+    /// The constraints of a context, which may only constrain `vars`.
+    fn context_constraints(
+        &self,
+        context: &[Constraint],
+        vars: &HashMap<String, TypeVar>,
+    ) -> Result<Vec<(String, TypeVar)>, TypeError> {
+        context
+            .iter()
+            .map(|constraint| {
+                if !self.classes.contains_key(constraint.class.name()) {
+                    return Err(TypeError::UnknownClass(constraint.class.clone()));
+                }
+                match vars.get(constraint.var.name()) {
+                    Some(&var) => Ok((constraint.class.name().to_string(), var)),
+                    None => Err(TypeError::UnboundTypeVariable(constraint.var.clone())),
+                }
+            })
+            .collect()
+    }
+
+    /// Register `instance` with the solver, once its type is known to take its parameters.
+    fn add_instance(&mut self, instance: &InstanceDecl) -> Result<(), TypeError> {
+        let arity = match self.type_arities.get(&instance.type_name) {
+            Some(&arity) => arity,
+            None if instance.type_name.starts_with('(') => instance.params.len(),
+            None => {
+                return Err(TypeError::UnknownInstanceType {
+                    class: instance.class.clone(),
+                    type_name: instance.type_name.clone(),
+                })
+            }
+        };
+        if instance.params.len() > arity {
+            return Err(TypeError::InstanceArity {
+                class: instance.class.clone(),
+                type_name: instance.type_name.clone(),
+                expected: arity,
+                found: instance.params.len(),
+            });
+        }
+        let context = instance
+            .context
+            .iter()
+            .map(|constraint| {
+                let index = instance_param(instance, constraint);
+                (constraint.class.name().to_string(), index)
+            })
+            .collect();
+        self.instances.insert(
+            (
+                instance.class.name().to_string(),
+                instance.type_name.clone(),
+            ),
+            Instance {
+                params: instance.params.len(),
+                context,
+                dictionary: dictionary_name(instance.class.name(), &instance.type_name),
+            },
+        );
+        Ok(())
+    }
+
+    /// Convert a constructor's signature into a type scheme which quantifies the parameters of
+    /// its data type. No other type variables may appear in its fields.
+    fn ctor_scheme(
+        &mut self,
+        data_decl: &DataDecl,
+        signature: &TypeExpr,
+    ) -> Result<Scheme, TypeError> {
+        let mut vars = HashMap::new();
+        for param in &data_decl.params {
+            let var = self.new_var();
+            vars.insert(param.name().to_string(), var);
+        }
+        let ty = self.type_from_expr(signature, &mut vars, false)?;
+        Ok(Scheme {
+            vars: vars.into_values().collect(),
+            constraints: Vec::new(),
+            ty,
+        })
+    }
+
+    fn type_from_expr(
+        &mut self,
+        type_expr: &TypeExpr,
+        vars: &mut HashMap<String, TypeVar>,
+        allow_new_vars: bool,
+    ) -> Result<Type, TypeError> {
+        match type_expr {
+            TypeExpr::Var(id) => match vars.get(id.name()) {
+                Some(&var) => Ok(Type::Var(var)),
+                None if allow_new_vars => {
+                    let var = self.new_var();
+                    vars.insert(id.name().to_string(), var);
+                    Ok(Type::Var(var))
+                }
+                None => Err(TypeError::UnboundTypeVariable(id.clone())),
+            },
+            TypeExpr::Con(id, args) => {
+                let Some(&arity) = self.type_arities.get(id.name()) else {
+                    return Err(TypeError::UnknownType(id.clone()));
+                };
+                if arity != args.len() {
+                    return Err(TypeError::TypeArity {
+                        name: id.clone(),
+                        expected: arity,
+                        found: args.len(),
+                    });
+                }
+                let args = args
+                    .iter()
+                    .map(|arg| self.type_from_expr(arg, vars, allow_new_vars))
+                    .collect::<Result<_, _>>()?;
+                Ok(Type::con(id.name(), args, id.location()))
+            }
+            TypeExpr::App(id, args) => {
+                let function =
+                    self.type_from_expr(&TypeExpr::Var(id.clone()), vars, allow_new_vars)?;
+                args.iter().try_fold(function, |function, arg| {
+                    Ok(Type::app(
+                        function,
+                        self.type_from_expr(arg, vars, allow_new_vars)?,
+                    ))
+                })
+            }
+            TypeExpr::Tuple(dims) => {
+                let dims = dims
+                    .iter()
+                    .map(|dim| self.type_from_expr(dim, vars, allow_new_vars))
+                    .collect::<Result<_, _>>()?;
+                Ok(Type::tuple(dims, Location::unknown()))
+            }
+            TypeExpr::Function(domain, range) => Ok(Type::function(
+                self.type_from_expr(domain, vars, allow_new_vars)?,
+                self.type_from_expr(range, vars, allow_new_vars)?,
+                Location::unknown(),
+            )),
+        }
+    }
+}
+
+/// The site which gave rise to `ty`, if it is known.
+fn origin(ty: &Type) -> Location {
+    match ty {
+        Type::Con { origin, .. } => *origin,
+        Type::Var(_) | Type::App(..) => Location::unknown(),
+    }
+}
+
+/// The position of the type variable of `constraint` among the parameters of `instance`, which
+/// the context of an instance may only constrain.
+fn instance_param(instance: &InstanceDecl, constraint: &Constraint) -> usize {
+    instance
+        .params
+        .iter()
+        .position(|param| param.name() == constraint.var.name())
+        .expect("instance contexts only constrain the instance's parameters")
+}
+
+pub(super) fn known_or(location: Location, fallback: Location) -> Location {
+    if location.is_known() {
+        location
+    } else {
+        fallback
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::location::Location;
+
+pub(crate) type TypeVar = usize;
+
+/// The name of the function type constructor, which takes a domain and a range.
+pub(crate) const FUNCTION: &str = "->";
+
+#[derive(Debug, Clone)]
+pub(crate) enum Type {
+    Var(TypeVar),
+    /// A type constructor applied to its arguments. `origin` is the site which gave rise to this
+    /// type, so that unification failures can point at both sides of a conflict.
+    Con {
+        name: String,
+        args: Vec<Type>,
+        origin: Location,
+    },
+    /// A type variable applied to an argument, such as the `m a` of `Monad m`. Once the variable
+    /// is solved with a constructor, the argument is appended to that constructor's arguments.
+    App(Box<Type>, Box<Type>),
+}
+
+impl Type {
+    pub fn con(name: &str, args: Vec<Type>, origin: Location) -> Self {
+        Self::Con {
+            name: name.to_string(),
+            args,
+            origin,
+        }
+    }
+    pub fn int(origin: Location) -> Self {
+        Self::con("Int", Vec::new(), origin)
+    }
+    pub fn string(origin: Location) -> Self {
+        Self::con("String", Vec::new(), origin)
+    }
+    pub fn float(origin: Location) -> Self {
+        Self::con("Float", Vec::new(), origin)
+    }
+    pub fn char(origin: Location) -> Self {
+        Self::con("Char", Vec::new(), origin)
+    }
+    pub fn function(domain: Type, range: Type, origin: Location) -> Self {
+        Self::con(FUNCTION, vec![domain, range], origin)
+    }
+    pub fn tuple(dims: Vec<Type>, origin: Location) -> Self {
+        Self::con(&tuple_name(dims.len()), dims, origin)
+    }
+    /// Apply `function` to `arg`, adding to its arguments if it is a constructor.
+    pub fn app(function: Type, arg: Type) -> Self {
+        match function {
+            Self::Con {
+                name,
+                mut args,
+                origin,
+            } => {
+                args.push(arg);
+                Self::Con { name, args, origin }
+            }
+            function => Self::App(Box::new(function), Box::new(arg)),
+        }
+    }
+}
+
+/// Tuple types are named after their arity, such as `()` and `(,)`.
+pub(crate) fn tuple_name(arity: usize) -> String {
+    format!("({})", ",".repeat(arity.saturating_sub(1)))
+}
+
+fn is_tuple_name(name: &str) -> bool {
+    name.starts_with('(')
+}
+
+/// A type which is polymorphic in `vars`. Each of `constraints` requires one of `vars` to be an
+/// instance of a class, and is passed a dictionary for it, in order, before any other argument.
+#[derive(Debug, Clone)]
+pub(crate) struct Scheme {
+    pub vars: Vec<TypeVar>,
+    pub constraints: Vec<(String, TypeVar)>,
+    pub ty: Type,
+}
+
+impl Scheme {
+    pub fn mono(ty: Type) -> Self {
+        Self {
+            vars: Vec::new(),
+            constraints: Vec::new(),
+            ty,
+        }
+    }
+}
+
+/// The name of the rigid type which stands for `var` while a binding is checked against its
+/// signature.
+pub(crate) fn skolem_name(var: TypeVar) -> String {
+    format!("'{var}")
+}
+
+/// Renders fully resolved types, naming type variables `a`, `b`, ... in order of appearance.
+/// Sharing one printer across several types keeps their variable names consistent. Rigid types
+/// are named after the variables they stand for.
+#[derive(Default)]
+pub(crate) struct TypePrinter {
+    names: HashMap<TypeVar, String>,
+}
+
+impl TypePrinter {
+    pub fn print(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Var(var) => self.var_name(*var),
+            Type::App(function, arg) => {
+                let function = self.print(function);
+                format!("{function} {}", self.print_arg(arg))
+            }
+            Type::Con { name, args, .. } if name == FUNCTION => {
+                let domain = match &args[0] {
+                    Type::Con { name, .. } if name == FUNCTION => {
+                        format!("({})", self.print(&args[0]))
+                    }
+                    domain => self.print(domain),
+                };
+                format!("{domain} -> {}", self.print(&args[1]))
+            }
+            Type::Con { name, args, .. } if is_tuple_name(name) => {
+                let dims: Vec<String> = args.iter().map(|arg| self.print(arg)).collect();
+                format!("({})", dims.join(", "))
+            }
+            Type::Con { name, args, .. } => {
+                let mut text = match name.strip_prefix('\'').map(str::parse::<TypeVar>) {
+                    Some(Ok(var)) => self.var_name(var),
+                    _ => name.clone(),
+                };
+                for arg in args {
+                    text.push(' ');
+                    text.push_str(&self.print_arg(arg));
+                }
+                text
+            }
+        }
+    }
+
+    /// Print the argument of a type application, which is parenthesized unless it is atomic.
+    pub fn print_arg(&mut self, arg: &Type) -> String {
+        match arg {
+            Type::Con { name, args, .. } if !args.is_empty() && !is_tuple_name(name) => {
+                format!("({})", self.print(arg))
+            }
+            Type::App(..) => format!("({})", self.print(arg)),
+            arg => self.print(arg),
+        }
+    }
+
+    fn var_name(&mut self, var: TypeVar) -> String {
+        let next = self.names.len();
+        self.names
+            .entry(var)
+            .or_insert_with(|| {
+                let letter = char::from(b'a' + (next % 26) as u8);
+                if next < 26 {
+                    letter.to_string()
+                } else {
+                    format!("{letter}{}", next / 26)
+                }
+            })
+            .clone()
+    }
+}
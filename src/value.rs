@@ -1,23 +1,145 @@
 #![allow(dead_code)]
 use std::{cell::RefCell, fmt::Debug, rc::Rc};
 
-use crate::{env::Env, id::Id, location::Location, token::Token};
+use num_bigint::BigInt;
+use num_traits::Signed;
+
+use crate::{
+    env::Env,
+    id::{CtorIdImpl, Id, IdErrorTrait},
+    location::Location,
+    token::Token,
+};
 
 #[derive(Debug, Clone)]
 pub enum Predicate {
     Irrefutable(Id),
+    /// `_`, which matches anything and binds nothing.
+    Wildcard(Location),
     Int(i64, Location),
-    Tuple(Vec<Predicate>),
+    /// An integer literal which does not fit in an `i64`.
+    BigInt(BigInt, Location),
+    Str(String, Location),
+    Char(char, Location),
+    Tuple(Vec<Predicate>, Location),
     Ctor(Id, Vec<Predicate>),
+    /// `name@predicate`, which binds `name` to the whole value matched by `predicate`.
+    As(Id, Box<Predicate>),
+    /// `~predicate`, which always matches. The value is only matched against `predicate` when
+    /// one of the names it binds is used.
+    Lazy(Box<Predicate>, Location),
+    /// `Person { name = predicate }`, which matches the named fields of a record constructor.
+    /// It is resolved into a `Ctor` once the fields of every constructor are known.
+    Record(Id, Vec<(Id, Predicate)>),
 }
 
 impl Predicate {
     pub fn location(&self) -> Location {
         match self {
-            Predicate::Irrefutable(id) => id.location(),
-            Predicate::Int(_, loc) => *loc,
-            Predicate::Tuple(predicates) => predicates[0].location(),
-            Predicate::Ctor(_, predicates) => predicates[0].location(),
+            Predicate::Irrefutable(id)
+            | Predicate::Ctor(id, _)
+            | Predicate::As(id, _)
+            | Predicate::Record(id, _) => id.location(),
+            Predicate::Wildcard(loc)
+            | Predicate::Int(_, loc)
+            | Predicate::BigInt(_, loc)
+            | Predicate::Str(_, loc)
+            | Predicate::Char(_, loc)
+            | Predicate::Tuple(_, loc)
+            | Predicate::Lazy(_, loc) => *loc,
+        }
+    }
+
+    /// Whether this predicate matches every value of its type.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Predicate::Irrefutable(_) | Predicate::Wildcard(_) | Predicate::Lazy(..) => true,
+            Predicate::As(_, predicate) => predicate.is_irrefutable(),
+            Predicate::Tuple(predicates, _) => predicates.iter().all(Predicate::is_irrefutable),
+            Predicate::Int(..)
+            | Predicate::BigInt(..)
+            | Predicate::Str(..)
+            | Predicate::Char(..)
+            | Predicate::Ctor(..)
+            | Predicate::Record(..) => false,
+        }
+    }
+
+    /// The names bound by this predicate, from left to right.
+    pub fn bindings(&self) -> Box<dyn Iterator<Item = &Id> + '_> {
+        match self {
+            Predicate::Irrefutable(id) => Box::new(std::iter::once(id)),
+            Predicate::Wildcard(_)
+            | Predicate::Int(..)
+            | Predicate::BigInt(..)
+            | Predicate::Str(..)
+            | Predicate::Char(..) => Box::new(std::iter::empty()),
+            Predicate::Tuple(predicates, _) | Predicate::Ctor(_, predicates) => {
+                Box::new(predicates.iter().flat_map(Predicate::bindings))
+            }
+            Predicate::As(id, predicate) => {
+                Box::new(std::iter::once(id).chain(predicate.bindings()))
+            }
+            Predicate::Lazy(predicate, _) => predicate.bindings(),
+            Predicate::Record(_, fields) => Box::new(
+                fields
+                    .iter()
+                    .flat_map(|(_, predicate)| predicate.bindings()),
+            ),
+        }
+    }
+}
+
+/// A predicate as it is written in the source, such as `Cons x (Just y)`.
+impl std::fmt::Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // An argument of a constructor, or the predicate of `@` or `~`, which needs parentheses.
+        let atomic = |predicate: &Predicate| match predicate {
+            Predicate::Ctor(_, args) if !args.is_empty() => format!("({predicate})"),
+            Predicate::Int(value, _) if *value < 0 => format!("({predicate})"),
+            Predicate::BigInt(value, _) if value.is_negative() => format!("({predicate})"),
+            predicate => predicate.to_string(),
+        };
+        match self {
+            Predicate::Irrefutable(id) => write!(f, "{id}"),
+            Predicate::Wildcard(_) => f.write_str("_"),
+            Predicate::Int(value, _) => write!(f, "{value}"),
+            Predicate::BigInt(value, _) => write!(f, "{value}"),
+            Predicate::Str(value, _) => write!(f, "{value:?}"),
+            Predicate::Char(value, _) => write!(f, "{value:?}"),
+            // A single parenthesized predicate is just grouping.
+            Predicate::Tuple(predicates, _) if predicates.len() == 1 => {
+                write!(f, "{}", predicates[0])
+            }
+            Predicate::Tuple(predicates, _) => {
+                f.write_str("(")?;
+                for (i, predicate) in predicates.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{predicate}")?;
+                }
+                f.write_str(")")
+            }
+            Predicate::Ctor(ctor, args) => {
+                write!(f, "{ctor}")?;
+                for arg in args {
+                    write!(f, " {}", atomic(arg))?;
+                }
+                Ok(())
+            }
+            Predicate::As(id, predicate) => write!(f, "{id}@{}", atomic(predicate)),
+            Predicate::Lazy(predicate, _) => write!(f, "~{}", atomic(predicate)),
+            Predicate::Record(ctor, fields) => {
+                write!(f, "{ctor} {{ ")?;
+                for (i, (field, predicate)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{field} = {predicate}")?;
+                }
+                f.write_str(" }")
+            }
         }
     }
 }
@@ -27,6 +149,249 @@ pub struct Decl {
     pub name: Id,
     pub patterns: Vec<Predicate>,
     pub body: Value,
+    /// The text of the doc comments before a top-level declaration, if any.
+    pub doc: Option<String>,
+}
+
+/// The syntax of a type, as written in a declaration.
+#[derive(Debug, Clone)]
+pub enum TypeExpr {
+    Var(Id),
+    /// A named type constructor applied to its arguments, such as `List a`.
+    Con(Id, Vec<TypeExpr>),
+    /// A type variable applied to arguments, such as the `m a` of `Monad m`.
+    App(Id, Vec<TypeExpr>),
+    Tuple(Vec<TypeExpr>),
+    Function(Box<TypeExpr>, Box<TypeExpr>),
+}
+
+/// A constructor in a `data` declaration, such as `Cons a (List a)`, or a record constructor
+/// such as `Person { name :: String, age :: Int }`.
+#[derive(Debug, Clone)]
+pub struct CtorDecl {
+    pub name: Id,
+    pub fields: Vec<TypeExpr>,
+    /// The name of each field of a record constructor, or nothing for other constructors.
+    pub field_names: Vec<Id>,
+}
+
+/// data List a = Nil | Cons a (List a);
+#[derive(Debug, Clone)]
+pub struct DataDecl {
+    pub name: Id,
+    pub params: Vec<Id>,
+    pub ctors: Vec<CtorDecl>,
+    pub doc: Option<String>,
+}
+
+impl DataDecl {
+    /// The type of a constructor function, such as `a -> List a -> List a` for `Cons`.
+    pub fn ctor_signature(&self, ctor: &CtorDecl) -> TypeExpr {
+        let result = TypeExpr::Con(
+            self.name.clone(),
+            self.params.iter().cloned().map(TypeExpr::Var).collect(),
+        );
+        ctor.fields.iter().rev().fold(result, |range, field| {
+            TypeExpr::Function(Box::new(field.clone()), Box::new(range))
+        })
+    }
+
+    /// The named fields of every constructor, without repeats, in the order in which they are
+    /// declared.
+    pub fn field_names(&self) -> Vec<&Id> {
+        let mut field_names: Vec<&Id> = Vec::new();
+        for ctor in &self.ctors {
+            for field_name in &ctor.field_names {
+                if !field_names
+                    .iter()
+                    .any(|seen| seen.name() == field_name.name())
+                {
+                    field_names.push(field_name);
+                }
+            }
+        }
+        field_names
+    }
+
+    /// The clauses of the accessor function of each named field, such as
+    /// `name (Person name _) = name;`. A field which several constructors share has a clause for
+    /// each of them, and a constructor without the field has a clause which is an error.
+    pub fn accessors(&self) -> Vec<Decl> {
+        self.field_names()
+            .into_iter()
+            .flat_map(|field_name| {
+                self.field_clauses(field_name)
+                    .into_iter()
+                    .map(move |pattern_expr| Decl {
+                        name: field_name.clone(),
+                        patterns: vec![pattern_expr.predicate],
+                        body: pattern_expr.expr,
+                        doc: None,
+                    })
+            })
+            .collect()
+    }
+
+    /// A clause for each constructor which selects `field` from it, such as
+    /// `Person name _ -> name`, or which is a `Value::NoField` if it lacks the field.
+    pub fn field_clauses(&self, field: &Id) -> Vec<PatternExpr> {
+        self.ctors
+            .iter()
+            .map(|ctor| {
+                let index = ctor
+                    .field_names
+                    .iter()
+                    .position(|name| name.name() == field.name());
+                let predicates = (0..ctor.fields.len())
+                    .map(|i| {
+                        if Some(i) == index {
+                            Predicate::Irrefutable(field.clone())
+                        } else {
+                            Predicate::Wildcard(field.location())
+                        }
+                    })
+                    .collect();
+                PatternExpr {
+                    predicate: Predicate::Ctor(ctor.name.clone(), predicates),
+                    expr: match index {
+                        Some(_) => Value::Id(field.clone()),
+                        None => Value::NoField {
+                            ctor: ctor.name.clone(),
+                            field: field.clone(),
+                        },
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// map :: (a -> b) -> List a -> List b;
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub name: Id,
+    /// The constraints before `=>`, as in `elem :: Eq a => a -> List a -> Bool`.
+    pub context: Vec<Constraint>,
+    pub type_expr: TypeExpr,
+    /// The doc comments before a top-level signature. The methods of a class have none.
+    pub doc: Option<String>,
+}
+
+/// The `Eq a` of `Eq a => ...`, which requires `var` to be an instance of `class`.
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    pub class: Id,
+    pub var: Id,
+}
+
+/// class Eq a => Ord a where compare :: a -> a -> Ordering; ...
+#[derive(Debug, Clone)]
+pub struct ClassDecl {
+    pub name: Id,
+    pub var: Id,
+    /// The constraints before `=>`, which must all be on `var`.
+    pub superclasses: Vec<Constraint>,
+    pub methods: Vec<Signature>,
+    /// Default definitions of methods, for instances which leave them out.
+    pub defaults: Vec<Decl>,
+    pub doc: Option<String>,
+}
+
+/// instance Eq a => Eq (List a) where ...
+#[derive(Debug, Clone)]
+pub struct InstanceDecl {
+    pub class: Id,
+    pub context: Vec<Constraint>,
+    /// The type constructor the instance is for, such as `List` or `(,)`, and the distinct type
+    /// variables it is applied to.
+    pub type_name: String,
+    pub params: Vec<Id>,
+    pub decls: Vec<Decl>,
+    pub doc: Option<String>,
+}
+
+/// How chains of operators with the same precedence are grouped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+    /// The operator cannot be chained with others of the same precedence.
+    None,
+}
+
+/// infixl 6 +, -;
+#[derive(Debug, Clone)]
+pub struct FixityDecl {
+    pub assoc: Assoc,
+    pub precedence: u8,
+    pub operators: Vec<Id>,
+    pub doc: Option<String>,
+}
+
+/// A top-level declaration in a program.
+#[derive(Debug, Clone)]
+pub enum Item {
+    Decl(Decl),
+    Data(DataDecl),
+    Signature(Signature),
+    Fixity(FixityDecl),
+    Class(ClassDecl),
+    Instance(InstanceDecl),
+}
+
+impl Item {
+    /// The names which the item declares, for looking up its documentation: the name of a
+    /// definition, signature, data type or class, each operator of a fixity declaration, and
+    /// `Class Type` for an instance.
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Item::Decl(Decl { name, .. })
+            | Item::Signature(Signature { name, .. })
+            | Item::Data(DataDecl { name, .. })
+            | Item::Class(ClassDecl { name, .. }) => vec![name.to_string()],
+            Item::Fixity(fixity_decl) => fixity_decl
+                .operators
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            Item::Instance(instance) => vec![format!("{} {}", instance.class, instance.type_name)],
+        }
+    }
+
+    /// Where the item starts, at the name it declares.
+    pub fn location(&self) -> Location {
+        match self {
+            Item::Decl(Decl { name, .. })
+            | Item::Signature(Signature { name, .. })
+            | Item::Data(DataDecl { name, .. })
+            | Item::Class(ClassDecl { name, .. })
+            | Item::Instance(InstanceDecl { class: name, .. }) => name.location(),
+            Item::Fixity(fixity_decl) => fixity_decl.operators[0].location(),
+        }
+    }
+
+    /// The text of the doc comments before the item, if any.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Item::Decl(Decl { doc, .. })
+            | Item::Data(DataDecl { doc, .. })
+            | Item::Signature(Signature { doc, .. })
+            | Item::Fixity(FixityDecl { doc, .. })
+            | Item::Class(ClassDecl { doc, .. })
+            | Item::Instance(InstanceDecl { doc, .. }) => doc.as_deref(),
+        }
+    }
+
+    pub fn with_doc(mut self, text: Option<String>) -> Self {
+        let (Item::Decl(Decl { doc, .. })
+        | Item::Data(DataDecl { doc, .. })
+        | Item::Signature(Signature { doc, .. })
+        | Item::Fixity(FixityDecl { doc, .. })
+        | Item::Class(ClassDecl { doc, .. })
+        | Item::Instance(InstanceDecl { doc, .. })) = &mut self;
+        *doc = text;
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +405,24 @@ pub struct CtorId {
     name: String,
 }
 
+impl CtorId {
+    pub fn new(id: &Id) -> Result<Self, CtorIdError> {
+        if CtorIdImpl::is_valid(id.name()) {
+            Ok(Self {
+                name: id.name().to_string(),
+            })
+        } else {
+            Err(CtorIdError(Token {
+                text: id.name().to_string(),
+                location: id.location(),
+            }))
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CtorIdError(pub Token);
 
@@ -54,23 +437,114 @@ impl std::fmt::Display for CtorIdError {
 }
 impl std::error::Error for CtorIdError {}
 
-pub type Builtin =
+/// The env in which a pending thunk will be evaluated.
+pub enum ThunkEnv {
+    /// Top-level declarations are evaluated in the global env, which is supplied by the runtime
+    /// since it cannot exist until every declaration has been built.
+    Global,
+    Local(Env),
+}
+
+/// The evaluation state of a `Value::Thunk`.
+pub enum ThunkState {
+    /// Not yet evaluated.
+    Pending {
+        env: ThunkEnv,
+        expr: Value,
+        location: Location,
+    },
+    /// Currently being evaluated. Forcing a blackholed thunk means its value depends on itself.
+    Blackhole(Location),
+    /// Evaluated to WHNF.
+    Evaluated(Value),
+}
+
+impl ThunkState {
+    /// Move the values which the thunk refers to into `values`, so that they can be dropped
+    /// without recursion.
+    fn take_values(&mut self, values: &mut Vec<Value>) {
+        match self {
+            ThunkState::Pending {
+                env: ThunkEnv::Local(env),
+                ..
+            } => take_bindings(std::mem::replace(env, Env::new()), values),
+            ThunkState::Evaluated(value) => values.push(std::mem::replace(value, Value::Null)),
+            _ => {}
+        }
+    }
+}
+
+/// Add the thunks which `env` binds to `values` before dropping `env`, so that those which only
+/// `env` refers to are not dropped along with it.
+fn take_bindings(env: Env, values: &mut Vec<Value>) {
+    values.extend(
+        env.values()
+            .filter(|value| matches!(value, Value::Thunk(_)))
+            .cloned(),
+    );
+}
+
+/// A thunk may refer to a long chain of other thunks, such as the cells of a long list or the
+/// unevaluated sums of an accumulator, so they are dropped one at a time rather than recursively.
+impl Drop for ThunkState {
+    fn drop(&mut self) {
+        let mut values = Vec::new();
+        self.take_values(&mut values);
+        while let Some(value) = values.pop() {
+            match value {
+                Value::Thunk(thunk) => {
+                    if let Ok(thunk) = Rc::try_unwrap(thunk) {
+                        thunk.into_inner().take_values(&mut values);
+                    }
+                }
+                Value::Tuple { dims } | Value::Ctor { dims, .. } => values.extend(dims),
+                Value::PartialApp { args, .. } => values.extend(args),
+                _ => {}
+            }
+        }
+    }
+}
+
+pub type BuiltinFn =
     dyn Fn(Vec<Value>) -> std::result::Result<Value, crate::runtime::error::RuntimeError>;
 
+/// A function implemented in Rust which is called once `arity` arguments have been applied.
+pub struct Builtin {
+    pub name: String,
+    pub arity: usize,
+    pub signature: TypeExpr,
+    /// Whether arguments are forced to WHNF before calling `func`. Lazy builtins receive their
+    /// arguments as thunks.
+    pub strict: bool,
+    pub func: Box<BuiltinFn>,
+}
+
 // Runtime values
 #[derive(Clone)]
 pub enum Value {
     Int(i64),
+    /// An integer which does not fit in an `i64`. Arithmetic on `Int`s is promoted to `BigInt`
+    /// rather than overflowing, and results which fit are demoted again.
+    BigInt(BigInt),
+    Float(f64),
     Str(String),
+    Char(char),
     Null,
     Lambda {
         param: Id,
         body: Box<Value>,
     },
+    /// A `Lambda` which has captured the env it was defined in.
+    Closure {
+        param: Id,
+        body: Rc<Value>,
+        env: Env,
+    },
     Id(Id),
     Match {
         subject: Box<Value>,
         pattern_exprs: Vec<PatternExpr>,
+        location: Location,
     },
     Callsite {
         function: Box<Value>,
@@ -79,27 +553,155 @@ pub enum Value {
     Tuple {
         dims: Vec<Value>,
     },
-    Thunk {
-        env: Option<Env>,
-        // Envs that share the same thunks will share the memoized value.
-        expr: Rc<RefCell<Value>>,
-    },
+    // Envs that share the same thunks will share the memoized value.
+    Thunk(Rc<RefCell<ThunkState>>),
     Builtin(Rc<Builtin>),
+    /// A builtin which has been applied to fewer than `arity` arguments.
+    PartialApp {
+        builtin: Rc<Builtin>,
+        args: Vec<Value>,
+    },
+    /// Recursive bindings which scope over each other and over `body`.
     Let {
-        name: Id,
-        value: Box<Value>,
+        bindings: Vec<(Id, Value)>,
         body: Box<Value>,
     },
     Ctor {
         name: CtorId,
         dims: Vec<Value>,
     },
+    /// A literal as written in the source, which evaluates to `value`.
+    Literal {
+        value: Box<Value>,
+        location: Location,
+    },
+    /// The right-hand side of a clause or match arm with guards, such as
+    /// `| x > 0 = 1 | otherwise = 2`. The body of the first alternative whose condition is `True`
+    /// is taken. When every condition is `False`, matching falls through to the next clause.
+    Guarded {
+        alternatives: Vec<(Value, Value)>,
+        location: Location,
+    },
+    /// A chain of infix operators as written in the source, such as `a + b * c`, where
+    /// `operators[i]` sits between `operands[i]` and `operands[i + 1]`. Grouping depends on the
+    /// fixity declarations of the whole program, so it is resolved into callsites after parsing.
+    /// `negations[i]` is the `-` which negates `operands[i]`, if any.
+    Infix {
+        operands: Vec<Value>,
+        operators: Vec<Id>,
+        negations: Vec<Option<Id>>,
+    },
+    /// `record { name = value }`, a copy of `record` with the named fields replaced. When
+    /// `record` is a constructor, as in `Person { name = value }`, every field must be given.
+    /// It is resolved into a match and a constructor call once the fields of every constructor
+    /// are known.
+    RecordUpdate {
+        record: Box<Value>,
+        fields: Vec<(Id, Value)>,
+        location: Location,
+    },
+    /// `record.field`, which is resolved into a match on the constructors of the type with the
+    /// field, so that a local binding named for the field cannot capture it.
+    FieldAccess {
+        record: Box<Value>,
+        field: Id,
+    },
+    /// The clause of a field accessor for a constructor which lacks the field, which is an error
+    /// when evaluated.
+    NoField {
+        ctor: Id,
+        field: Id,
+    },
 }
 
 impl Value {
     pub(crate) fn builtin(f: Rc<Builtin>) -> Self {
         Self::Builtin(f)
     }
+    pub(crate) fn bool(b: bool) -> Self {
+        let name = if b { "True" } else { "False" };
+        Self::Ctor {
+            name: CtorId {
+                name: name.to_string(),
+            },
+            dims: Vec::new(),
+        }
+    }
+    /// The constructor of the prelude's `Ordering` for `ordering`.
+    pub(crate) fn ordering(ordering: std::cmp::Ordering) -> Self {
+        let name = match ordering {
+            std::cmp::Ordering::Less => "LT",
+            std::cmp::Ordering::Equal => "EQ",
+            std::cmp::Ordering::Greater => "GT",
+        };
+        Self::Ctor {
+            name: CtorId {
+                name: name.to_string(),
+            },
+            dims: Vec::new(),
+        }
+    }
+    /// An integer, which is only big when it does not fit in an `i64`.
+    pub(crate) fn integer(value: BigInt) -> Self {
+        match i64::try_from(&value) {
+            Ok(value) => Self::Int(value),
+            Err(_) => Self::BigInt(value),
+        }
+    }
+    pub(crate) fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Value::Int(value) => Some(BigInt::from(*value)),
+            Value::BigInt(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+    pub(crate) fn thunk(env: Env, expr: Value, location: Location) -> Self {
+        Self::pending(ThunkEnv::Local(env), expr, location)
+    }
+    /// A constant applicative form: a top-level value which is evaluated at most once per
+    /// program run, and shared by every use.
+    pub(crate) fn global_thunk(expr: Value, location: Location) -> Self {
+        Self::pending(ThunkEnv::Global, expr, location)
+    }
+    fn pending(env: ThunkEnv, expr: Value, location: Location) -> Self {
+        Self::Thunk(Rc::new(RefCell::new(ThunkState::Pending {
+            env,
+            expr,
+            location,
+        })))
+    }
+    /// A best-effort source location for an expression.
+    pub fn location(&self) -> Location {
+        match self {
+            Value::Id(id) => id.location(),
+            Value::Lambda { param, .. } => param.location(),
+            Value::Match { location, .. } => *location,
+            Value::Callsite { function, .. } => function.location(),
+            Value::Let { body, .. } => body.location(),
+            Value::Literal { location, .. } => *location,
+            Value::Guarded { location, .. } => *location,
+            Value::Infix {
+                operands,
+                negations,
+                ..
+            } => match &negations[0] {
+                Some(minus) => minus.location(),
+                None => operands[0].location(),
+            },
+            Value::RecordUpdate { location, .. } => *location,
+            Value::FieldAccess { field, .. } | Value::NoField { field, .. } => field.location(),
+            _ => Location::unknown(),
+        }
+    }
+    /// Whether matching may fall through this right-hand side of a clause to the next clause,
+    /// since it has guards. The guards may be under the bindings of a `where` block.
+    pub fn is_guarded(&self) -> bool {
+        match self {
+            Value::Guarded { .. } => true,
+            Value::Let { body, .. } => body.is_guarded(),
+            _ => false,
+        }
+    }
     /*pub(crate) fn id(name: impl AsRef<str>) -> Self {
         Self::Id(Id::new(name))
     }*/
@@ -107,10 +709,14 @@ impl Value {
         matches!(
             self,
             Value::Int(_)
+                | Value::BigInt(_)
+                | Value::Float(_)
                 | Value::Str(_)
-                | Value::Lambda { .. }
+                | Value::Char(_)
+                | Value::Closure { .. }
                 | Value::Ctor { .. }
                 | Value::Builtin { .. }
+                | Value::PartialApp { .. }
                 | Value::Tuple { .. }
         )
     }
@@ -119,6 +725,9 @@ impl Debug for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Int(x) => write!(f, "{x}"),
+            Value::BigInt(x) => write!(f, "{x}"),
+            Value::Float(x) => write!(f, "{x:?}"),
+            Value::Char(c) => write!(f, "{c:?}"),
             Value::Str(x) => {
                 f.write_str("\"")?;
                 for c in x.chars() {
@@ -132,12 +741,14 @@ impl Debug for Value {
                 }
                 f.write_str("\"")
             }
-            Value::Null => todo!(),
+            Value::Null => f.write_str("null"),
             Value::Lambda { param, body } => write!(f, "λ{param}.{body:?}"),
+            Value::Closure { param, body, .. } => write!(f, "λ{param}.{body:?}"),
             Value::Id(id) => f.write_str(id.name()),
             Value::Match {
                 subject,
                 pattern_exprs,
+                ..
             } => {
                 write!(f, "match {subject:?} {{ {pattern_exprs:?} }}",)
             }
@@ -147,14 +758,75 @@ impl Debug for Value {
                 let mut delim = "";
                 for dim in dims {
                     write!(f, "{delim}{:?}", dim)?;
-                    delim = " ";
+                    delim = ", ";
+                }
+                f.write_str(")")
+            }
+            Value::Thunk(thunk) => match &*thunk.borrow() {
+                ThunkState::Pending { .. } => f.write_str("<thunk>"),
+                ThunkState::Blackhole(_) => f.write_str("<blackhole>"),
+                // An evaluated thunk may be the head of a long list, which would take too long to
+                // format, or overflow the stack.
+                ThunkState::Evaluated(_) => f.write_str("<evaluated>"),
+            },
+            Value::Builtin(builtin) => write!(f, "<builtin {}>", builtin.name),
+            Value::PartialApp { builtin, args } => {
+                write!(f, "(<builtin {}>", builtin.name)?;
+                for arg in args {
+                    write!(f, " {arg:?}")?;
                 }
                 f.write_str(")")
             }
-            Value::Thunk { .. } => todo!(),
-            Value::Builtin(_) => "<builtin>".fmt(f),
-            Value::Let { .. } => todo!(),
-            Value::Ctor { .. } => todo!(),
+            Value::Let { bindings, body } => {
+                f.write_str("let ")?;
+                let mut delim = "";
+                for (name, value) in bindings {
+                    write!(f, "{delim}{name} = {value:?}")?;
+                    delim = "; ";
+                }
+                write!(f, " : {body:?}")
+            }
+            Value::Ctor { name, dims } => {
+                if dims.is_empty() {
+                    return f.write_str(name.name());
+                }
+                write!(f, "({}", name.name())?;
+                for dim in dims {
+                    write!(f, " {dim:?}")?;
+                }
+                f.write_str(")")
+            }
+            Value::Literal { value, .. } => write!(f, "{value:?}"),
+            Value::Guarded { alternatives, .. } => {
+                let mut delim = "";
+                for (condition, body) in alternatives {
+                    write!(f, "{delim}| {condition:?} = {body:?}")?;
+                    delim = " ";
+                }
+                Ok(())
+            }
+            Value::Infix {
+                operands,
+                operators,
+                negations,
+            } => {
+                let negation = |i: usize| if negations[i].is_some() { "-" } else { "" };
+                write!(f, "{{{}{:?}", negation(0), operands[0])?;
+                for (i, operator) in operators.iter().enumerate() {
+                    write!(f, " {operator} {}{:?}", negation(i + 1), operands[i + 1])?;
+                }
+                f.write_str("}")
+            }
+            Value::RecordUpdate { record, fields, .. } => {
+                write!(f, "{record:?} {{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    let delim = if i == 0 { " " } else { ", " };
+                    write!(f, "{delim}{name} = {value:?}")?;
+                }
+                f.write_str(" }")
+            }
+            Value::FieldAccess { record, field } => write!(f, "{record:?}.{field}"),
+            Value::NoField { ctor, field } => write!(f, "<{ctor} has no field {field}>"),
         }
     }
 }